@@ -5,6 +5,7 @@ extern crate test;
 extern crate tokio_core;
 
 use futures::{Async, Poll, future, task};
+use synchrotron::Void;
 
 #[bench]
 fn busy_synchrotron_main(b: &mut test::Bencher) {
@@ -21,15 +22,69 @@ fn busy_synchrotron_main(b: &mut test::Bencher) {
 #[bench]
 fn busy_synchrotron_spawn(b: &mut test::Bencher) {
     let mut core = synchrotron::Core::default();
-    core.handle().spawn(future::poll_fn(|| {
+    core.handle().spawn(future::poll_fn(|| -> Poll<(), Void> {
         task::park().unpark();
         Ok(Async::NotReady)
-    }));
+    })).unwrap();
+    b.iter(|| {
+        core.turn::<()>();
+    });
+}
+
+#[bench]
+fn spawn_handle(b: &mut test::Bencher) {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
     b.iter(|| {
+        handle.spawn(Ok::<(), Void>(())).unwrap();
         core.turn::<()>();
     });
 }
 
+#[bench]
+fn spawn_core(b: &mut test::Bencher) {
+    let mut core = synchrotron::Core::default();
+    b.iter(|| {
+        core.spawn(Ok::<(), Void>(())).unwrap();
+        core.turn::<()>();
+    });
+}
+
+#[bench]
+fn spawn_future_ready(b: &mut test::Bencher) {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    b.iter(|| {
+        core.run(handle.spawn_future(Ok::<_, ()>(()))).unwrap();
+    });
+}
+
+#[bench]
+fn spawn_future_inline(b: &mut test::Bencher) {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    b.iter(|| {
+        core.run(handle.spawn_future_inline(Ok::<_, ()>(()))).unwrap();
+    });
+}
+
+#[bench]
+fn spawn_future_pending(b: &mut test::Bencher) {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    b.iter(|| {
+        let mut polled = false;
+        core.run(handle.spawn_future(future::poll_fn(move || {
+            if polled {
+                return Ok::<_, ()>(Async::Ready(()));
+            }
+            polled = true;
+            task::park().unpark();
+            Ok(Async::NotReady)
+        }))).unwrap();
+    });
+}
+
 #[bench]
 fn busy_tokio(b: &mut test::Bencher) {
     let mut core = tokio_core::reactor::Core::new().unwrap();
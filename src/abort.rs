@@ -0,0 +1,84 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures::{Async, Future, Poll};
+use futures::task::{self, Task};
+use void::Void;
+
+#[derive(Debug, Default)]
+struct Inner {
+    aborted: AtomicBool,
+    // the task running the abortable future
+    task: Mutex<Option<Task>>,
+}
+
+/// Aborts a task spawned with
+/// [`Handle::spawn_abortable`](struct.Handle.html#method.spawn_abortable).
+///
+/// The handle can be cloned and sent to other threads.
+#[derive(Clone, Debug)]
+pub struct AbortHandle(Arc<Inner>);
+
+/// The task's end of an [`AbortHandle`](struct.AbortHandle.html), to be
+/// passed to
+/// [`Handle::spawn_abortable`](struct.Handle.html#method.spawn_abortable).
+#[derive(Debug)]
+pub struct AbortRegistration(Arc<Inner>);
+
+impl AbortHandle {
+    /// Create an `AbortHandle` along with the registration for the task it
+    /// aborts.
+    pub fn new_pair() -> (AbortHandle, AbortRegistration) {
+        let inner = Arc::new(Inner::default());
+        (AbortHandle(inner.clone()), AbortRegistration(inner))
+    }
+
+    /// Abort the task.  It is woken up, and the next time it is polled it
+    /// finishes without polling its future, which is then dropped along
+    /// with the task.  Does nothing if the task has already finished.
+    pub fn abort(&self) {
+        self.0.aborted.store(true, Ordering::SeqCst);
+        let task = self.0.task.lock().unwrap().take();
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+
+    /// Whether [`abort`](#method.abort) was called.
+    pub fn is_aborted(&self) -> bool {
+        self.0.aborted.load(Ordering::SeqCst)
+    }
+}
+
+/// A future that finishes early once its task is aborted.
+#[derive(Debug)]
+pub struct Abortable<F> {
+    future: F,
+    inner: Arc<Inner>,
+}
+
+impl<F> Abortable<F> {
+    pub fn new(future: F, registration: AbortRegistration) -> Self {
+        Abortable {
+            future: future,
+            inner: registration.0,
+        }
+    }
+}
+
+impl<F: Future<Item=(), Error=Void>> Future for Abortable<F> {
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<(), Void> {
+        *self.inner.task.lock().unwrap() = Some(task::park());
+        let poll = if self.inner.aborted.load(Ordering::SeqCst) {
+            Ok(Async::Ready(()))
+        } else {
+            self.future.poll()
+        };
+        if let Ok(Async::Ready(())) = poll {
+            // the task is finished, so there is nothing left to wake
+            self.inner.task.lock().unwrap().take();
+        }
+        poll
+    }
+}
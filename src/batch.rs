@@ -0,0 +1,87 @@
+//! Batching of wake-ups that happen on the core thread.
+//!
+//! While a task is being polled, unparking other tasks of the same core only
+//! records their indices.  They are pushed into the ready queue under a
+//! single lock once the poll returns, which saves a lot of lock traffic when
+//! one task wakes many others.
+
+use std::cell::RefCell;
+use std::mem;
+use std::sync::Arc;
+use super::SharedQueue;
+
+type Batch = Option<(SharedQueue, Vec<usize>)>;
+
+thread_local! {
+    static BATCH: RefCell<Batch> = RefCell::new(None);
+}
+
+/// Restores the enclosing batch (if polls are nested) and flushes the
+/// current one, even if the poll panics.
+struct Flush(Batch);
+
+impl Drop for Flush {
+    fn drop(&mut self) {
+        let saved = self.0.take();
+        let batch = BATCH.with(|batch| {
+            mem::replace(&mut *batch.borrow_mut(), saved)
+        });
+        if let Some((queue, indices)) = batch {
+            if indices.is_empty() {
+                return;
+            }
+            if let Ok(mut queue) = queue.lock() {
+                for index in indices {
+                    queue.push_back(index);
+                }
+            }
+        }
+    }
+}
+
+/// Call `poll`, deferring the wake-ups on `queue` that happen during the
+/// call until it returns.
+pub fn poll_batched<R, F: FnOnce() -> R>(queue: &SharedQueue, poll: F) -> R {
+    let saved = BATCH.with(|batch| {
+        batch.borrow_mut().replace((queue.clone(), Vec::new()))
+    });
+    let _flush = Flush(saved);
+    poll()
+}
+
+/// Record a wake-up of `index` if a poll of a task on `queue` is in
+/// progress on this thread.  Returns `false` if the caller must push the
+/// index itself.
+pub fn defer(queue: &SharedQueue, index: usize) -> bool {
+    BATCH.try_with(|batch| match *batch.borrow_mut() {
+        Some((ref current, ref mut indices))
+            if Arc::ptr_eq(current, queue) => {
+            indices.push(index);
+            true
+        }
+        _ => false,
+    }).unwrap_or(false)
+}
+
+/// Number of tasks waiting in the ready queue of the core whose task is
+/// being polled on this thread, including deferred wake-ups.  Returns `None`
+/// outside of a poll.
+pub fn queue_depth() -> Option<usize> {
+    BATCH.try_with(|batch| match *batch.borrow() {
+        Some((ref queue, ref indices)) => {
+            Some(queue.lock().unwrap().len() + indices.len())
+        }
+        None => None,
+    }).unwrap_or(None)
+}
+
+/// Drop any deferred wake-up of `index` on `queue`.
+pub fn cancel(queue: &SharedQueue, index: usize) {
+    let _ = BATCH.try_with(|batch| {
+        if let Some((ref current, ref mut indices)) = *batch.borrow_mut() {
+            if Arc::ptr_eq(current, queue) {
+                indices.retain(|&i| i != index);
+            }
+        }
+    });
+}
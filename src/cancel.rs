@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::rc::{Rc, Weak};
+use futures::{Async, Future, Poll};
+use futures::task::{self, Task};
+use void::Void;
+
+#[derive(Default)]
+struct Node {
+    cancelled: bool,
+    waiters: Vec<Task>,
+    children: Vec<Weak<RefCell<Node>>>,
+}
+
+/// A flag that tells tasks to stop, with a tree of child tokens that are
+/// cancelled along with it.
+///
+/// Cloning a token yields another reference to the same token.  Tasks can
+/// wait for cancellation with [`cancelled`](#method.cancelled), or be
+/// spawned with
+/// [`Handle::spawn_cancellable`](struct.Handle.html#method.spawn_cancellable)
+/// to be dropped as soon as the token is cancelled.  Tokens are meant to be
+/// used on the thread of a single core.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Rc<RefCell<Node>>);
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a token that is cancelled when this one is, but can also be
+    /// cancelled on its own without affecting this one.
+    pub fn child_token(&self) -> Self {
+        let child = CancellationToken::new();
+        let mut node = self.0.borrow_mut();
+        if node.cancelled {
+            child.0.borrow_mut().cancelled = true;
+        } else {
+            node.children.retain(|child| child.upgrade().is_some());
+            node.children.push(Rc::downgrade(&child.0));
+        }
+        child
+    }
+
+    /// Cancel this token and all its descendants, waking the tasks waiting
+    /// for any of them.
+    pub fn cancel(&self) {
+        let (waiters, children) = {
+            let mut node = self.0.borrow_mut();
+            if node.cancelled {
+                return;
+            }
+            node.cancelled = true;
+            (mem::take(&mut node.waiters),
+             mem::take(&mut node.children))
+        };
+        for waiter in waiters {
+            waiter.unpark();
+        }
+        for child in children.into_iter().filter_map(|child| child.upgrade()) {
+            CancellationToken(child).cancel();
+        }
+    }
+
+    /// Whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.borrow().cancelled
+    }
+
+    /// A future that completes once the token is cancelled.
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled(self.clone())
+    }
+}
+
+/// A future that completes once a
+/// [`CancellationToken`](struct.CancellationToken.html) is cancelled.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Cancelled(CancellationToken);
+
+impl Future for Cancelled {
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<(), Void> {
+        let mut node = (self.0).0.borrow_mut();
+        if node.cancelled {
+            return Ok(Async::Ready(()));
+        }
+        if !node.waiters.iter().any(Task::will_notify_current) {
+            node.waiters.push(task::park());
+        }
+        Ok(Async::NotReady)
+    }
+}
@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use futures::{Async, Poll, Stream};
+use futures::task::{self, Task};
+use void::Void;
+use super::TaskId;
+
+/// How a spawned task finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TaskOutcome {
+    /// The task ran to completion.
+    Completed,
+    /// The future given to
+    /// [`Handle::spawn`](struct.Handle.html#method.spawn) failed.
+    Failed,
+    /// The task was dropped before it completed, such as when its core was
+    /// shut down, reset or dropped.
+    Dropped,
+}
+
+/// Information about a spawned task that has finished.
+///
+/// See [`Core::completions`](struct.Core.html#method.completions).
+#[derive(Clone, Debug)]
+pub struct TaskCompletion {
+    id: TaskId,
+    name: Option<Cow<'static, str>>,
+    outcome: TaskOutcome,
+    duration: Duration,
+}
+
+impl TaskCompletion {
+    /// Id of the task.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Name of the task, if it was spawned with
+    /// [`Handle::spawn_named`](struct.Handle.html#method.spawn_named).
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// How the task finished.
+    pub fn outcome(&self) -> TaskOutcome {
+        self.outcome
+    }
+
+    /// Time elapsed between spawning the task and its completion.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    queue: VecDeque<TaskCompletion>,
+    task: Option<Task>,
+    closed: bool,
+}
+
+/// A stream of the tasks of a [`Core`](struct.Core.html) that have finished,
+/// in the order they finished.
+///
+/// The stream ends when the `Core` is dropped.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Completions(Rc<RefCell<Shared>>);
+
+impl Stream for Completions {
+    type Item = TaskCompletion;
+    type Error = Void;
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut shared = self.0.borrow_mut();
+        if let Some(completion) = shared.queue.pop_front() {
+            return Ok(Async::Ready(Some(completion)));
+        }
+        if shared.closed {
+            return Ok(Async::Ready(None));
+        }
+        shared.task = Some(task::park());
+        Ok(Async::NotReady)
+    }
+}
+
+/// The core's end of the completion streams.
+#[derive(Debug, Default)]
+pub struct Publisher(Vec<Weak<RefCell<Shared>>>);
+
+impl Publisher {
+    pub fn subscribe(&mut self) -> Completions {
+        let shared = Rc::new(RefCell::new(Shared::default()));
+        self.0.push(Rc::downgrade(&shared));
+        Completions(shared)
+    }
+
+    pub fn publish(&mut self,
+                   id: TaskId,
+                   name: &Option<Cow<'static, str>>,
+                   outcome: TaskOutcome,
+                   duration: Duration) {
+        if self.0.is_empty() {
+            return;
+        }
+        self.0.retain(|shared| match shared.upgrade() {
+            None => false,
+            Some(shared) => {
+                let mut shared = shared.borrow_mut();
+                shared.queue.push_back(TaskCompletion {
+                    id: id,
+                    name: name.clone(),
+                    outcome: outcome,
+                    duration: duration,
+                });
+                shared.task.take().map(|task| task.unpark());
+                true
+            }
+        });
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        for shared in &self.0 {
+            if let Some(shared) = shared.upgrade() {
+                let mut shared = shared.borrow_mut();
+                shared.closed = true;
+                shared.task.take().map(|task| task.unpark());
+            }
+        }
+    }
+}
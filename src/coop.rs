@@ -0,0 +1,168 @@
+//! Cooperative preemption of long-running polls.
+//!
+//! Every time a core polls a task, it gives the task a budget of `coop`
+//! calls.  Once the budget is exhausted the task is asked to yield, so that
+//! a task doing a lot of work in a single poll does not hold up the others
+//! for too long.
+
+use std::cell::Cell;
+use futures::{Async, Poll, Stream, task};
+
+/// Number of `coop` calls allowed in a single poll of a task.
+const BUDGET: u32 = 128;
+
+thread_local! {
+    // `None` outside of a poll by a core
+    static REMAINING: Cell<Option<u32>> = Cell::new(None);
+    // number of polls started on this thread, to tell them apart
+    static POLLS: Cell<u64> = Cell::new(0);
+}
+
+/// Restores the budget of the enclosing poll, if polls are nested.
+struct Restore(Option<u32>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let saved = self.0;
+        let _ = REMAINING.try_with(|remaining| remaining.set(saved));
+    }
+}
+
+/// Call `poll` with a fresh budget.
+pub fn with_budget<R, F: FnOnce() -> R>(poll: F) -> R {
+    let saved = REMAINING.with(|remaining| remaining.replace(Some(BUDGET)));
+    POLLS.with(|polls| polls.set(polls.get() + 1));
+    let _restore = Restore(saved);
+    poll()
+}
+
+/// Consume one unit of the budget of the task being polled.
+///
+/// Returns `NotReady` once the budget of the current poll is exhausted,
+/// after unparking the current task so that it is polled again later; the
+/// caller should then return `NotReady` itself.  Outside of a poll by a
+/// [`Core`](struct.Core.html), this always returns `Ready`.
+///
+/// The [`coop!`](macro.coop.html) macro does both in one step.
+pub fn coop() -> Async<()> {
+    let exhausted = REMAINING.try_with(|remaining| match remaining.get() {
+        Some(0) => true,
+        Some(n) => {
+            remaining.set(Some(n - 1));
+            false
+        }
+        None => false,
+    }).unwrap_or(false);
+    if exhausted {
+        task::park().unpark();
+        Async::NotReady
+    } else {
+        Async::Ready(())
+    }
+}
+
+/// Yield from the enclosing `poll` function if the current task has used up
+/// its budget, as determined by [`coop`](fn.coop.html).
+///
+/// This is meant to be sprinkled inside long loops of synchronous work.  The
+/// enclosing function must return a `Poll`.
+///
+/// ```
+/// #[macro_use]
+/// extern crate synchrotron;
+/// extern crate futures;
+///
+/// use futures::{Async, Poll};
+///
+/// fn poll_sum(items: &mut Vec<u64>, sum: &mut u64) -> Poll<u64, ()> {
+///     while !items.is_empty() {
+///         coop!();
+///         *sum += items.pop().unwrap();
+///     }
+///     Ok(Async::Ready(*sum))
+/// }
+///
+/// fn main() {
+///     let mut core = synchrotron::Core::default();
+///     let mut items = (1..1001).collect();
+///     let mut sum = 0;
+///     let total = core.run(futures::future::poll_fn(|| {
+///         poll_sum(&mut items, &mut sum)
+///     }));
+///     assert_eq!(total, Ok(500500));
+/// }
+/// ```
+#[macro_export]
+macro_rules! coop {
+    () => {
+        if let $crate::__Async::NotReady = $crate::coop() {
+            return Ok($crate::__Async::NotReady);
+        }
+    }
+}
+
+/// Extra combinators for streams.
+pub trait StreamExt: Stream + Sized {
+    /// Yield after `limit` items within a single poll of the task, so that
+    /// a stream that always has items ready (such as a full channel) does
+    /// not starve the other tasks of the core.
+    ///
+    /// After `limit` items, the stream returns `NotReady` once, after
+    /// unparking the current task so that it is polled again on a later
+    /// turn.  Panics if `limit` is zero.
+    fn cooperative(self, limit: usize) -> Cooperative<Self> {
+        assert!(limit > 0, "limit must be positive");
+        Cooperative {
+            stream: self,
+            limit: limit,
+            poll: None,
+            yielded: 0,
+        }
+    }
+}
+
+impl<S: Stream> StreamExt for S {}
+
+/// A stream that yields to the other tasks regularly.
+///
+/// Created by
+/// [`StreamExt::cooperative`](trait.StreamExt.html#method.cooperative).
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Cooperative<S> {
+    stream: S,
+    limit: usize,
+    // the poll in which the items were yielded
+    poll: Option<u64>,
+    yielded: usize,
+}
+
+impl<S> Cooperative<S> {
+    /// Recover the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Stream> Stream for Cooperative<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        let poll = POLLS.with(|polls| polls.get());
+        if self.poll != Some(poll) {
+            self.poll = Some(poll);
+            self.yielded = 0;
+        }
+        if self.yielded == self.limit {
+            // outside of a core, every poll looks like the same one
+            self.yielded = 0;
+            task::park().unpark();
+            return Ok(Async::NotReady);
+        }
+        let item = self.stream.poll()?;
+        if let Async::Ready(Some(_)) = item {
+            self.yielded += 1;
+        }
+        Ok(item)
+    }
+}
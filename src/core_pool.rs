@@ -0,0 +1,125 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use futures::{IntoFuture, future};
+use void::Void;
+use super::{Core, Handle};
+
+/// A pool of idle [`Core`](struct.Core.html)s, so that running a future on
+/// a core of its own, such as once per request, does not allocate a new one
+/// each time.
+///
+/// A core is [reset](struct.Core.html#method.reset) when it is given back
+/// to the pool, which keeps its allocations.  Its hooks, settings and
+/// context values are kept as well, so they should be set up the same way
+/// on every core of the pool.  Handles kept from a previous use stop
+/// working once the core is given back, as if the core were dropped.
+pub struct CorePool<'a> {
+    idle: RefCell<Vec<Core<'a>>>,
+    // the number of idle cores beyond which cores given back are dropped
+    max_idle: usize,
+}
+
+impl<'a> fmt::Debug for CorePool<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CorePool")
+            .field("idle", &self.idle.borrow().len())
+            .field("max_idle", &self.max_idle)
+            .finish()
+    }
+}
+
+impl<'a> CorePool<'a> {
+    /// Create an empty pool that keeps up to `max_idle` cores.
+    pub fn new(max_idle: usize) -> Self {
+        CorePool {
+            idle: RefCell::new(Vec::with_capacity(max_idle)),
+            max_idle: max_idle,
+        }
+    }
+
+    /// Fill the pool with new cores, up to its size, each of them having
+    /// run `tasks` tasks at once, so that their storage is allocated before
+    /// they are needed.
+    pub fn warm_up(&self, tasks: usize) {
+        let mut idle = self.idle.borrow_mut();
+        while idle.len() < self.max_idle {
+            let mut core = Core::default();
+            let handle = core.handle();
+            for _ in 0..tasks {
+                handle.spawn_or_drop(future::lazy(|| Ok::<(), Void>(())));
+            }
+            core.run_all();
+            core.reset();
+            idle.push(core);
+        }
+    }
+
+    /// Number of idle cores in the pool.
+    pub fn idle(&self) -> usize {
+        self.idle.borrow().len()
+    }
+
+    /// Take an idle core out of the pool, or create one if there is none.
+    /// The core is given back to the pool when the returned guard is
+    /// dropped.
+    pub fn get(&self) -> PooledCore<'_, 'a> {
+        let core = self.idle.borrow_mut().pop().unwrap_or_default();
+        PooledCore {
+            pool: self,
+            core: Some(core),
+        }
+    }
+
+    /// Like [`scoped_run`](fn.scoped_run.html), but on a core of the pool.
+    pub fn scoped_run<F, R>(&self, f: F) -> Result<R::Item, R::Error>
+        where F: FnOnce(&Handle<'a>) -> R, R: IntoFuture
+    {
+        let mut core = self.get();
+        let future = f(&core.handle());
+        core.run_until_done(future)
+    }
+}
+
+/// A core taken out of a [`CorePool`](struct.CorePool.html), which is
+/// given back once this is dropped.
+pub struct PooledCore<'p, 'a: 'p> {
+    pool: &'p CorePool<'a>,
+    // only None while being dropped
+    core: Option<Core<'a>>,
+}
+
+impl<'p, 'a> fmt::Debug for PooledCore<'p, 'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PooledCore")
+            .field(&self.core)
+            .finish()
+    }
+}
+
+impl<'p, 'a> Deref for PooledCore<'p, 'a> {
+    type Target = Core<'a>;
+    fn deref(&self) -> &Core<'a> {
+        self.core.as_ref().unwrap()
+    }
+}
+
+impl<'p, 'a> DerefMut for PooledCore<'p, 'a> {
+    fn deref_mut(&mut self) -> &mut Core<'a> {
+        self.core.as_mut().unwrap()
+    }
+}
+
+impl<'p, 'a> Drop for PooledCore<'p, 'a> {
+    fn drop(&mut self) {
+        let mut core = self.core.take().unwrap();
+        // first, so that nothing the tasks do as they are dropped reaches
+        // the next user
+        core.retire_handles();
+        core.reset();
+        let mut idle = self.pool.idle.borrow_mut();
+        if idle.len() < self.pool.max_idle {
+            idle.push(core);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use futures::{Async, Future, IntoFuture, Poll};
+use futures::executor::Unpark;
+use futures::task::{self, Task};
+use void::Void;
+use super::{CancellationToken, Handle, JoinHandle};
+
+/// Identifies a task added to a [`TaskGraph`](struct.TaskGraph.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+type NodeBox<'a, E> = Box<Future<Item=(), Error=E> + 'a>;
+
+/// A set of tasks that depend on each other, to be spawned together with
+/// [`Handle::spawn_graph`](struct.Handle.html#method.spawn_graph).
+///
+/// A task can only depend on tasks added before it, so the graph has no
+/// cycles.
+pub struct TaskGraph<'a, E> {
+    nodes: Vec<(NodeBox<'a, E>, Vec<NodeId>)>,
+}
+
+impl<'a, E> Default for TaskGraph<'a, E> {
+    fn default() -> Self {
+        TaskGraph { nodes: Vec::new() }
+    }
+}
+
+impl<'a, E> fmt::Debug for TaskGraph<'a, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.nodes.iter().map(|&(_, ref after)| after))
+            .finish()
+    }
+}
+
+impl<'a, E> TaskGraph<'a, E> {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a task that is not polled until the tasks in `after` have
+    /// finished.
+    ///
+    /// Panics if a task in `after` is not part of this graph.
+    pub fn add<F>(&mut self, after: &[NodeId], f: F) -> NodeId
+        where F: IntoFuture<Item=(), Error=E>, F::Future: 'a
+    {
+        let id = NodeId(self.nodes.len());
+        assert!(after.iter().all(|&dependency| dependency < id),
+                "dependency is not part of the graph");
+        self.nodes.push((Box::new(f.into_future()), after.to_vec()));
+        id
+    }
+
+    /// Number of tasks in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the graph has no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<'a, E: 'a> TaskGraph<'a, E> {
+    pub(crate) fn spawn(self, handle: &Handle<'a>) -> GraphFuture<E> {
+        let token = CancellationToken::new();
+        let shared = Rc::new(RefCell::new(Shared {
+            running: self.nodes.len(),
+            error: None,
+            waiter: None,
+        }));
+        let mut finished: Vec<JoinHandle<(), Void>> = Vec::new();
+        for (future, after) in self.nodes {
+            let node = Node(shared.clone());
+            let cancel = token.clone();
+            let future = future.then(move |result| {
+                if let Err(err) = result {
+                    node.fail(err);
+                    cancel.cancel();
+                }
+                Ok::<_, Void>(())
+            });
+            let (task, done) = JoinHandle::new(
+                token.cancelled().select(future).then(|_| Ok(())));
+            if let Some((ticket, _)) = handle.spawn_parked(task) {
+                if after.is_empty() {
+                    ticket.unpark();
+                } else {
+                    let countdown = Arc::new(Countdown {
+                        remaining: AtomicUsize::new(after.len()),
+                        ticket: ticket,
+                    });
+                    for dependency in after {
+                        finished[dependency.0]
+                            .unpark_when_finished(countdown.clone());
+                    }
+                }
+            }
+            finished.push(done);
+        }
+        GraphFuture {
+            shared: shared,
+            token: token,
+        }
+    }
+}
+
+/// Queues a task once all its dependencies have unparked it.
+struct Countdown {
+    remaining: AtomicUsize,
+    ticket: Arc<Unpark>,
+}
+
+impl Unpark for Countdown {
+    fn unpark(&self) {
+        if self.remaining.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.ticket.unpark();
+        }
+    }
+}
+
+struct Shared<E> {
+    // tasks that have not finished or been dropped yet
+    running: usize,
+    // the first error from a task
+    error: Option<E>,
+    waiter: Option<Task>,
+}
+
+/// A task's end: records its error, and counts it out of the graph when it
+/// is dropped, whether it finished or not.
+struct Node<E>(Rc<RefCell<Shared<E>>>);
+
+impl<E> Node<E> {
+    fn fail(&self, err: E) {
+        let mut shared = self.0.borrow_mut();
+        if shared.error.is_none() {
+            shared.error = Some(err);
+        }
+    }
+}
+
+impl<E> Drop for Node<E> {
+    fn drop(&mut self) {
+        let waiter = {
+            let mut shared = self.0.borrow_mut();
+            shared.running -= 1;
+            if shared.running > 0 {
+                return;
+            }
+            shared.waiter.take()
+        };
+        if let Some(waiter) = waiter {
+            waiter.unpark();
+        }
+    }
+}
+
+/// A future that resolves once all the tasks of a
+/// [`TaskGraph`](struct.TaskGraph.html) have finished.
+///
+/// Created by [`Handle::spawn_graph`](struct.Handle.html#method.spawn_graph).
+/// If a task fails, the tasks that have not finished yet are dropped, and
+/// the future fails with that error once they are.  Dropping a `GraphFuture`
+/// drops the remaining tasks too.
+#[must_use = "futures do nothing unless polled"]
+pub struct GraphFuture<E> {
+    shared: Rc<RefCell<Shared<E>>>,
+    token: CancellationToken,
+}
+
+impl<E> fmt::Debug for GraphFuture<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shared = self.shared.borrow();
+        f.debug_struct("GraphFuture")
+            .field("running", &shared.running)
+            .field("failed", &shared.error.is_some())
+            .finish()
+    }
+}
+
+impl<E> Future for GraphFuture<E> {
+    type Item = ();
+    type Error = E;
+    fn poll(&mut self) -> Poll<(), E> {
+        let mut shared = self.shared.borrow_mut();
+        if shared.running > 0 {
+            shared.waiter = Some(task::park());
+            return Ok(Async::NotReady);
+        }
+        match shared.error.take() {
+            Some(err) => Err(err),
+            None => Ok(Async::Ready(())),
+        }
+    }
+}
+
+impl<E> Drop for GraphFuture<E> {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
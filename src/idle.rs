@@ -0,0 +1,286 @@
+use std::{fmt, mem};
+use std::cell::{Cell, RefCell};
+use std::sync::{self, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+use futures::task::Task;
+use index_queue::IndexQueue;
+use wait_handle::Signal;
+use super::{ReadyQueue, SharedQueue};
+
+/// A signal that a blocking [`WaitHandle`](struct.WaitHandle.html) waits
+/// for, and the task to wake once it arrives.
+struct Waiter {
+    signal: Arc<Signal>,
+    // cleared once the signal has been waited for; gone if the `WaitHandle`
+    // was dropped
+    watching: sync::Weak<AtomicBool>,
+    task: Task,
+}
+
+thread_local! {
+    // the signals that the idle strategies of the cores turned on this
+    // thread wait on in place of parking
+    static WAITERS: RefCell<Vec<Waiter>> = RefCell::new(Vec::new());
+    // number of idle strategies on this thread
+    static PARKERS: Cell<usize> = Cell::new(0);
+}
+
+/// Have the idle strategy of the core turned on this thread wait on `signal`
+/// when it would park, and then wake `task`.  Returns `false`, registering
+/// nothing, if no core on this thread has an idle strategy that parks.
+pub fn wait_when_idle(signal: Arc<Signal>,
+                      watching: &Arc<AtomicBool>,
+                      task: Task) -> bool {
+    if PARKERS.with(Cell::get) == 0 {
+        return false;
+    }
+    WAITERS.with(|waiters| {
+        let mut waiters = waiters.borrow_mut();
+        waiters.retain(|waiter| waiter.watching.upgrade().is_some());
+        waiters.push(Waiter {
+            signal: signal,
+            watching: Arc::downgrade(watching),
+            task: task,
+        });
+    });
+    true
+}
+
+/// The oldest waiter whose `WaitHandle` is still alive, if any.
+fn next_waiter() -> Option<Waiter> {
+    WAITERS.with(|waiters| {
+        let mut waiters = waiters.borrow_mut();
+        while !waiters.is_empty() {
+            let waiter = waiters.remove(0);
+            if waiter.watching.upgrade().is_some() {
+                return Some(waiter);
+            }
+        }
+        None
+    })
+}
+
+/// How a [`Core`](struct.Core.html) waits when all its tasks are parked.
+///
+/// After a turn that finds nothing to do, the core first keeps spinning for
+/// `spins` turns, then yields its time slice for `yields` more turns, and
+/// finally parks its thread until a task is woken or `park_timeout` elapses.
+///
+/// The core also tracks how long it has recently been idle before a task
+/// was woken.  While that is below `spin_below` on average, it keeps
+/// spinning for up to `spin_below` even after `spins` turns, because a
+/// wake-up is expected soon.
+///
+/// See [`Core::set_idle_strategy`](struct.Core.html#method.set_idle_strategy).
+#[derive(Clone, Copy, Debug)]
+pub struct IdleStrategy {
+    /// Number of idle turns spent spinning.  Defaults to 1000.
+    pub spins: u32,
+    /// Number of idle turns after the spinning ones that yield the thread.
+    /// Defaults to 100.
+    pub yields: u32,
+    /// Longest time the thread is parked before turning again, which
+    /// bounds the latency of futures sent through an
+    /// [`Injector`](struct.Injector.html).  Defaults to 10 milliseconds.
+    pub park_timeout: Duration,
+    /// Keep spinning for this long if the recent idle periods were shorter
+    /// than this on average.  Defaults to 50 microseconds.
+    pub spin_below: Duration,
+}
+
+impl Default for IdleStrategy {
+    fn default() -> Self {
+        IdleStrategy {
+            spins: 1000,
+            yields: 100,
+            park_timeout: Duration::from_millis(10),
+            spin_below: Duration::from_micros(50),
+        }
+    }
+}
+
+/// What the idle strategy of a core has done so far.
+///
+/// See [`Core::idle_stats`](struct.Core.html#method.idle_stats).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IdleStats {
+    /// Number of idle turns that were followed by spinning.
+    pub spins: u64,
+    /// Number of idle turns that were followed by yielding the thread.
+    pub yields: u64,
+    /// Number of times the thread was parked.
+    pub parks: u64,
+    /// Moving average of how long the core was idle before a task was
+    /// woken, if it has been idle at all.
+    pub mean_idle: Option<Duration>,
+}
+
+/// The core thread and whether it is parked, shared with the wakers.
+struct Sleeper {
+    parked: AtomicBool,
+    thread: Thread,
+}
+
+impl fmt::Debug for Sleeper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sleeper")
+            .field("parked", &self.parked.load(Ordering::Relaxed))
+            .field("thread", &self.thread.id())
+            .finish()
+    }
+}
+
+/// Wraps the ready queue of a core so that pushing an index unparks the core
+/// thread if needed.
+struct ParkingQueue {
+    queue: Box<ReadyQueue>,
+    sleeper: Arc<Sleeper>,
+}
+
+impl fmt::Debug for ParkingQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParkingQueue")
+            .field("queue", &self.queue)
+            .field("parked", &self.sleeper.parked.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl ReadyQueue for ParkingQueue {
+    fn push_back(&mut self, index: usize) {
+        self.queue.push_back(index);
+        if self.sleeper.parked.load(Ordering::SeqCst) {
+            self.sleeper.thread.unpark();
+        }
+    }
+
+    fn pop_front(&mut self) -> Option<usize> {
+        self.queue.pop_front()
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.queue.remove(index);
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.queue.contains(index)
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/// The idle strategy of a core and its state.
+#[derive(Debug)]
+pub struct Idle {
+    strategy: IdleStrategy,
+    stats: IdleStats,
+    // consecutive idle turns
+    idle_turns: u32,
+    idle_since: Option<Instant>,
+    sleeper: Arc<Sleeper>,
+}
+
+impl Idle {
+    /// Set up `strategy` for the core that owns `queue`, which must be
+    /// turned on the current thread.
+    pub fn new(strategy: IdleStrategy, queue: &SharedQueue) -> Self {
+        let sleeper = Arc::new(Sleeper {
+            parked: AtomicBool::new(false),
+            thread: thread::current(),
+        });
+        let mut queue = queue.lock().unwrap();
+        let inner = mem::replace(&mut *queue,
+                                 Box::new(IndexQueue::default()));
+        *queue = Box::new(ParkingQueue {
+            queue: inner,
+            sleeper: sleeper.clone(),
+        });
+        PARKERS.with(|parkers| parkers.set(parkers.get() + 1));
+        Idle {
+            strategy: strategy,
+            stats: IdleStats::default(),
+            idle_turns: 0,
+            idle_since: None,
+            sleeper: sleeper,
+        }
+    }
+
+    pub fn set_strategy(&mut self, strategy: IdleStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn stats(&self) -> IdleStats {
+        self.stats
+    }
+
+    /// Clear the statistics and forget the current idle period.
+    pub fn reset(&mut self) {
+        self.stats = IdleStats::default();
+        self.idle_turns = 0;
+        self.idle_since = None;
+    }
+
+    /// Record a turn that polled a task.
+    pub fn busy(&mut self) {
+        self.idle_turns = 0;
+        if let Some(since) = self.idle_since.take() {
+            let idle = since.elapsed();
+            self.stats.mean_idle = Some(match self.stats.mean_idle {
+                None => idle,
+                Some(mean) => (mean * 7 + idle) / 8,
+            });
+        }
+    }
+
+    /// Record a turn that found nothing to do, and wait as the strategy
+    /// says.
+    pub fn idle(&mut self, queue: &SharedQueue) {
+        let since = *self.idle_since.get_or_insert_with(Instant::now);
+        self.idle_turns = self.idle_turns.saturating_add(1);
+        let spin_below = self.strategy.spin_below;
+        let expect_wake = match self.stats.mean_idle {
+            Some(mean) => mean < spin_below && since.elapsed() < spin_below,
+            None => false,
+        };
+        if expect_wake || self.idle_turns <= self.strategy.spins {
+            self.stats.spins += 1;
+        } else if self.idle_turns - self.strategy.spins
+            <= self.strategy.yields {
+            self.stats.yields += 1;
+            thread::yield_now();
+        } else {
+            self.stats.parks += 1;
+            self.park(queue);
+        }
+    }
+
+    /// Park the thread, or wait on the signal of a blocking `WaitHandle` if
+    /// there is one, since then the thread is woken once it arrives.
+    fn park(&self, queue: &SharedQueue) {
+        self.sleeper.parked.store(true, Ordering::SeqCst);
+        // a task may have been woken before the flag was set
+        if queue.lock().unwrap().is_empty() {
+            match next_waiter() {
+                Some(waiter) => {
+                    waiter.signal.wait();
+                    if let Some(watching) = waiter.watching.upgrade() {
+                        watching.store(false, Ordering::SeqCst);
+                    }
+                    waiter.task.unpark();
+                }
+                None => thread::park_timeout(self.strategy.park_timeout),
+            }
+        }
+        self.sleeper.parked.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Idle {
+    fn drop(&mut self) {
+        PARKERS.with(|parkers| parkers.set(parkers.get() - 1));
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use futures::{Future, IntoFuture};
+use void::Void;
+
+type InjectedBox = Box<Future<Item=(), Error=Void> + Send>;
+
+/// A queue through which other threads can hand futures to a
+/// [`Core`](struct.Core.html).
+///
+/// Cloned injectors refer to the same queue.  Once registered with
+/// [`Core::add_injector`](struct.Core.html#method.add_injector), the queue is
+/// drained at the start of every turn and each future is spawned as its own
+/// task.
+#[derive(Clone, Default)]
+pub struct Injector(Arc<Mutex<VecDeque<InjectedBox>>>);
+
+impl fmt::Debug for Injector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Injector")
+            .field(&self.0.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Injector {
+    /// Create an empty injection queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a future to be spawned on the core.
+    pub fn push<F>(&self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: Send + 'static
+    {
+        self.push_boxed(Box::new(f.into_future()))
+    }
+
+    /// Queue an already boxed future to be spawned on the core.
+    pub fn push_boxed(&self, f: InjectedBox) {
+        self.0.lock().unwrap().push_back(f);
+    }
+
+    /// Remove all the queued futures.
+    pub(crate) fn take_all(&self) -> VecDeque<InjectedBox> {
+        let mut queue = self.0.lock().unwrap();
+        if queue.is_empty() {
+            VecDeque::new()
+        } else {
+            queue.split_off(0)
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::cell::Cell;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+use super::TaskId;
+
+/// An unpark of a task, as seen by a
+/// [`WakeInterceptor`](trait.WakeInterceptor.html).
+#[derive(Clone, Debug)]
+pub struct Wake {
+    task: TaskId,
+    thread: ThreadId,
+    at: Instant,
+    tag: u64,
+}
+
+impl Wake {
+    pub(crate) fn new(task: TaskId) -> Self {
+        Wake {
+            task: task,
+            thread: thread::current().id(),
+            at: Instant::now(),
+            tag: 0,
+        }
+    }
+
+    /// Id of the task that was unparked.
+    pub fn task(&self) -> TaskId {
+        self.task
+    }
+
+    /// The thread the unpark happened on.
+    pub fn thread(&self) -> ThreadId {
+        self.thread
+    }
+
+    /// When the unpark happened.
+    pub fn at(&self) -> Instant {
+        self.at
+    }
+
+    /// A value attached by the interceptor, zero unless set.
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Attach a value to the unpark, which is handed back to
+    /// [`on_poll`](trait.WakeInterceptor.html#method.on_poll).
+    pub fn set_tag(&mut self, tag: u64) {
+        self.tag = tag;
+    }
+}
+
+/// Observes the unparks of the tasks of a [`Core`](struct.Core.html).
+///
+/// Registered with [`Core::set_wake_interceptor`][set].  Tasks may be
+/// unparked from any thread, so the interceptor is shared between threads.
+/// It is called after the task's waker is released, so it may unpark tasks
+/// itself, but those unparks are not intercepted.
+///
+/// [set]: struct.Core.html#method.set_wake_interceptor
+pub trait WakeInterceptor: Send + Sync {
+    /// Called on every unpark of a task, on the thread doing the unpark.
+    fn on_unpark(&self, wake: &mut Wake);
+
+    /// Called on the core thread right before a task is polled because of
+    /// `wake`, the first unpark since its previous poll.  `delay` is the time
+    /// elapsed since that unpark.
+    fn on_poll(&self, wake: &Wake, delay: Duration) {
+        let _ = (wake, delay);
+    }
+
+    /// Called on the core thread when a spawned task finishes, so that any
+    /// state kept about it can be dropped.
+    fn on_complete(&self, task: TaskId) {
+        let _ = task;
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    // checked first, so that unparks skip the lock when there is none
+    installed: AtomicBool,
+    interceptor: Mutex<Option<Arc<WakeInterceptor>>>,
+}
+
+/// The interceptor of a core, shared with the wakers of all its tasks.
+#[derive(Clone, Default)]
+pub struct InterceptorSlot(Arc<Slot>);
+
+impl fmt::Debug for InterceptorSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("InterceptorSlot")
+            .field(&self.is_installed())
+            .finish()
+    }
+}
+
+impl InterceptorSlot {
+    pub fn set(&self, interceptor: Arc<WakeInterceptor>) {
+        *self.0.interceptor.lock().unwrap() = Some(interceptor);
+        self.0.installed.store(true, Ordering::Release);
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.0.installed.load(Ordering::Acquire)
+    }
+
+    pub fn get(&self) -> Option<Arc<WakeInterceptor>> {
+        if !self.is_installed() {
+            return None;
+        }
+        self.0.interceptor.lock().unwrap().clone()
+    }
+}
+
+thread_local! {
+    // whether an interceptor is being called on this thread
+    static INTERCEPTING: Cell<bool> = Cell::new(false);
+}
+
+/// Call `on_unpark` unless the unpark comes from an interceptor, and return
+/// whether it was called.
+pub fn on_unpark(interceptor: &WakeInterceptor, wake: &mut Wake) -> bool {
+    struct Reset;
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            INTERCEPTING.with(|intercepting| intercepting.set(false));
+        }
+    }
+
+    if INTERCEPTING.with(|intercepting| intercepting.replace(true)) {
+        return false;
+    }
+    let _reset = Reset;
+    interceptor.on_unpark(wake);
+    true
+}
+
+/// Both interceptors observe every event, the first one first.
+impl<A: WakeInterceptor, B: WakeInterceptor> WakeInterceptor for (A, B) {
+    fn on_unpark(&self, wake: &mut Wake) {
+        self.0.on_unpark(wake);
+        self.1.on_unpark(wake);
+    }
+
+    fn on_poll(&self, wake: &Wake, delay: Duration) {
+        self.0.on_poll(wake, delay);
+        self.1.on_poll(wake, delay);
+    }
+
+    fn on_complete(&self, task: TaskId) {
+        self.0.on_complete(task);
+        self.1.on_complete(task);
+    }
+}
@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::{fmt, mem};
+use std::rc::Rc;
+use std::sync::Arc;
+use futures::{Async, Future, Poll};
+use futures::executor::Unpark;
+use futures::task::{self, Task};
+use void::Void;
+use super::TaskId;
+
+/// Why a [`JoinHandle`](struct.JoinHandle.html) did not yield an item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinError<E> {
+    /// The task failed with this error.
+    Failed(E),
+    /// The task was dropped before it finished, e.g. because its core was
+    /// dropped.
+    Canceled,
+}
+
+impl<E: fmt::Display> fmt::Display for JoinError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JoinError::Failed(ref err) => write!(f, "task failed: {}", err),
+            JoinError::Canceled => f.write_str("task was canceled"),
+        }
+    }
+}
+
+impl<E: Error> Error for JoinError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            JoinError::Failed(_) => "task failed",
+            JoinError::Canceled => "task was canceled",
+        }
+    }
+}
+
+struct Shared<T, E> {
+    id: Option<TaskId>,
+    result: Option<Result<T, E>>,
+    done: bool,
+    // whether the task finished with an item, even once it was taken
+    succeeded: bool,
+    waiter: Option<Task>,
+    // tasks spawned to run after this one
+    dependents: Vec<Arc<Unpark>>,
+}
+
+/// The task's end: records the result, or the cancellation if dropped
+/// without one.
+struct Completer<T, E>(Rc<RefCell<Shared<T, E>>>);
+
+impl<T, E> Completer<T, E> {
+    fn complete(&self, result: Result<T, E>) {
+        let mut shared = self.0.borrow_mut();
+        shared.succeeded = result.is_ok();
+        shared.result = Some(result);
+    }
+}
+
+impl<T, E> Drop for Completer<T, E> {
+    fn drop(&mut self) {
+        let (waiter, dependents) = {
+            let mut shared = self.0.borrow_mut();
+            shared.done = true;
+            (shared.waiter.take(), mem::take(&mut shared.dependents))
+        };
+        if let Some(waiter) = waiter {
+            waiter.unpark();
+        }
+        for dependent in dependents {
+            dependent.unpark();
+        }
+    }
+}
+
+/// The future that is actually spawned by
+/// [`Handle::spawn_handle`](struct.Handle.html#method.spawn_handle).
+pub struct JoinTask<F: Future> {
+    future: F,
+    completer: Completer<F::Item, F::Error>,
+}
+
+impl<F: Future> Future for JoinTask<F> {
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<(), Void> {
+        let result = match self.future.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(item)) => Ok(item),
+            Err(err) => Err(err),
+        };
+        self.completer.complete(result);
+        Ok(Async::Ready(()))
+    }
+}
+
+/// A future that resolves to the output of a spawned task.
+///
+/// Created by [`Handle::spawn_handle`](struct.Handle.html#method.spawn_handle).
+/// Dropping the `JoinHandle` does not affect the task.
+#[must_use = "futures do nothing unless polled"]
+pub struct JoinHandle<T, E>(Rc<RefCell<Shared<T, E>>>);
+
+impl<T, E> JoinHandle<T, E> {
+    /// Wrap `future` so that its output is sent to the returned handle.
+    pub(crate) fn new<F>(future: F) -> (JoinTask<F>, Self)
+        where F: Future<Item=T, Error=E>
+    {
+        let shared = Rc::new(RefCell::new(Shared {
+            id: None,
+            result: None,
+            done: false,
+            succeeded: false,
+            waiter: None,
+            dependents: Vec::new(),
+        }));
+        let task = JoinTask {
+            future: future,
+            completer: Completer(shared.clone()),
+        };
+        (task, JoinHandle(shared))
+    }
+
+    pub(crate) fn set_id(&self, id: TaskId) {
+        self.0.borrow_mut().id = Some(id);
+    }
+
+    /// The id of the task, or `None` if it was never spawned because its
+    /// core was gone.
+    pub fn id(&self) -> Option<TaskId> {
+        self.0.borrow().id
+    }
+
+    /// Whether the task has finished or was dropped.
+    pub fn is_finished(&self) -> bool {
+        self.0.borrow().done
+    }
+
+    /// Unpark `dependent` once the task has finished or was dropped, or
+    /// right away if it already has.
+    pub(crate) fn unpark_when_finished(&self, dependent: Arc<Unpark>) {
+        let mut shared = self.0.borrow_mut();
+        if shared.done {
+            drop(shared);
+            dependent.unpark();
+        } else {
+            shared.dependents.push(dependent);
+        }
+    }
+
+    /// A future that resolves once the task has finished with an item, or
+    /// fails once it has failed or was dropped.
+    pub(crate) fn succeeded(&self) -> Succeeded<T, E> {
+        Succeeded(self.0.clone())
+    }
+}
+
+/// Created by `JoinHandle::succeeded`.  It is only meant to be polled once
+/// the task has finished, as it does not register for wake-ups.
+pub(crate) struct Succeeded<T, E>(Rc<RefCell<Shared<T, E>>>);
+
+impl<T, E> Future for Succeeded<T, E> {
+    type Item = ();
+    type Error = ();
+    fn poll(&mut self) -> Poll<(), ()> {
+        let shared = self.0.borrow();
+        if !shared.done {
+            Ok(Async::NotReady)
+        } else if shared.succeeded {
+            Ok(Async::Ready(()))
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl<T, E> fmt::Debug for JoinHandle<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shared = self.0.borrow();
+        f.debug_struct("JoinHandle")
+            .field("id", &shared.id)
+            .field("done", &shared.done)
+            .finish()
+    }
+}
+
+impl<T, E> Future for JoinHandle<T, E> {
+    type Item = T;
+    type Error = JoinError<E>;
+    fn poll(&mut self) -> Poll<T, JoinError<E>> {
+        let mut shared = self.0.borrow_mut();
+        match shared.result.take() {
+            Some(Ok(item)) => Ok(Async::Ready(item)),
+            Some(Err(err)) => Err(JoinError::Failed(err)),
+            None if shared.done => Err(JoinError::Canceled),
+            None => {
+                shared.waiter = Some(task::park());
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+use futures::{Async, Future, IntoFuture, Poll, Stream};
+use futures::task::{self, Task};
+use super::{CancellationToken, Handle};
+
+struct Shared<T, E> {
+    // outputs of the finished tasks that have not been yielded yet
+    ready: VecDeque<Result<T, E>>,
+    // tasks that have not finished or been dropped yet
+    running: usize,
+    waiter: Option<Task>,
+}
+
+/// The task's end: sends the output to the set, and tells it the task is
+/// gone when dropped, whether it finished or not.
+struct Member<T, E>(Rc<RefCell<Shared<T, E>>>);
+
+impl<T, E> Member<T, E> {
+    fn finish(&self, result: Result<T, E>) {
+        self.0.borrow_mut().ready.push_back(result);
+    }
+}
+
+impl<T, E> Drop for Member<T, E> {
+    fn drop(&mut self) {
+        let waiter = {
+            let mut shared = self.0.borrow_mut();
+            shared.running -= 1;
+            shared.waiter.take()
+        };
+        if let Some(waiter) = waiter {
+            waiter.unpark();
+        }
+    }
+}
+
+/// A set of tasks whose outputs are yielded as a `Stream` in the order the
+/// tasks complete.
+///
+/// Created by [`Handle::join_set`](struct.Handle.html#method.join_set).
+/// Unlike [`LocalFuturesUnordered`](struct.LocalFuturesUnordered.html), each
+/// future runs as a task of its own, so it makes progress even when the
+/// set is not being polled.
+///
+/// The stream ends when the set is empty.  An error from a task is yielded as
+/// the stream error and the remaining tasks are left in the set.  Dropping
+/// the set aborts the tasks that are still running.
+#[must_use = "streams do nothing unless polled"]
+pub struct JoinSet<'a, T, E> {
+    handle: Handle<'a>,
+    token: CancellationToken,
+    shared: Rc<RefCell<Shared<T, E>>>,
+}
+
+impl<'a, T, E> JoinSet<'a, T, E> {
+    pub(crate) fn new(handle: Handle<'a>) -> Self {
+        JoinSet {
+            handle: handle,
+            token: CancellationToken::new(),
+            shared: Rc::new(RefCell::new(Shared {
+                ready: VecDeque::new(),
+                running: 0,
+                waiter: None,
+            })),
+        }
+    }
+
+    /// Number of tasks in the set: those still running and those whose
+    /// output has not been yielded yet.
+    pub fn len(&self) -> usize {
+        let shared = self.shared.borrow();
+        shared.running + shared.ready.len()
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Abort the tasks that are still running.  Each of them is dropped the
+    /// next time the core gets to it, instead of being polled.  Outputs that
+    /// are already available can still be taken, and tasks spawned later
+    /// are not affected.
+    pub fn abort_all(&mut self) {
+        self.token.cancel();
+        self.token = CancellationToken::new();
+    }
+}
+
+impl<'a, T: 'a, E: 'a> JoinSet<'a, T, E> {
+    /// Spawn a future as a task of the set.
+    pub fn spawn<F>(&mut self, f: F)
+        where F: IntoFuture<Item=T, Error=E>, F::Future: 'a
+    {
+        self.shared.borrow_mut().running += 1;
+        let member = Member(self.shared.clone());
+        self.handle.spawn_cancellable(f.into_future().then(move |result| {
+            member.finish(result);
+            Ok(())
+        }), &self.token);
+    }
+}
+
+impl<'a, T, E> fmt::Debug for JoinSet<'a, T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shared = self.shared.borrow();
+        f.debug_struct("JoinSet")
+            .field("running", &shared.running)
+            .field("ready", &shared.ready.len())
+            .finish()
+    }
+}
+
+impl<'a, T, E> Stream for JoinSet<'a, T, E> {
+    type Item = T;
+    type Error = E;
+    fn poll(&mut self) -> Poll<Option<T>, E> {
+        let mut shared = self.shared.borrow_mut();
+        match shared.ready.pop_front() {
+            Some(Ok(item)) => Ok(Async::Ready(Some(item))),
+            Some(Err(err)) => Err(err),
+            None if shared.running == 0 => Ok(Async::Ready(None)),
+            None => {
+                shared.waiter = Some(task::park());
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+impl<'a, T, E> Drop for JoinSet<'a, T, E> {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
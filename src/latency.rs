@@ -0,0 +1,134 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use intercept::{Wake, WakeInterceptor};
+use super::TaskId;
+
+/// Number of buckets: one for zero, and one for each power of two.
+const BUCKETS: usize = 65;
+
+/// A histogram of durations with power-of-two buckets.
+///
+/// Bucket `i > 0` counts the durations of at least `2^(i - 1)` and less than
+/// `2^i` nanoseconds, so quantiles are accurate to within a factor of two.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    max: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            max: Duration::from_secs(0),
+        }
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    duration.as_secs().saturating_mul(1_000_000_000)
+        .saturating_add(duration.subsec_nanos() as u64)
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a duration to the histogram.
+    pub fn record(&mut self, duration: Duration) {
+        let bucket = 64 - nanos(duration).leading_zeros() as usize;
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.max = cmp::max(self.max, duration);
+    }
+
+    /// Number of durations recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Longest duration recorded.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Upper bound of the `q`-quantile of the recorded durations, for `q`
+    /// between 0 and 1.  Never more than [`max`](#method.max).
+    pub fn quantile(&self, q: f64) -> Duration {
+        let rank = (q * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= cmp::max(rank, 1) {
+                let bound = if bucket == 0 { 0 } else { 1 << (bucket - 1) };
+                let bound = Duration::new(bound / 1_000_000_000,
+                                          (bound % 1_000_000_000) as u32);
+                return cmp::min(bound * 2, self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Counts of the buckets, see above.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+}
+
+#[derive(Debug, Default)]
+struct Histograms {
+    total: LatencyHistogram,
+    tasks: HashMap<TaskId, LatencyHistogram>,
+}
+
+/// Records the delay between each unpark of a task and its subsequent poll.
+///
+/// Created by
+/// [`Core::record_wake_latency`](struct.Core.html#method.record_wake_latency).
+/// The histogram of a task is dropped when the task finishes, but its
+/// samples remain part of the [`total`](#method.total).
+#[derive(Clone, Debug, Default)]
+pub struct WakeLatency(Arc<Mutex<Histograms>>);
+
+impl WakeLatency {
+    /// Histogram of the wake-to-poll delays of all tasks.
+    pub fn total(&self) -> LatencyHistogram {
+        self.0.lock().unwrap().total.clone()
+    }
+
+    /// Histogram of the wake-to-poll delays of a task that has not finished
+    /// yet.
+    pub fn task(&self, id: TaskId) -> Option<LatencyHistogram> {
+        self.0.lock().unwrap().tasks.get(&id).cloned()
+    }
+
+    /// Histograms of all the tasks that have not finished yet.
+    pub fn tasks(&self) -> Vec<(TaskId, LatencyHistogram)> {
+        let histograms = self.0.lock().unwrap();
+        let mut tasks: Vec<_> = histograms.tasks.iter()
+            .map(|(&id, histogram)| (id, histogram.clone()))
+            .collect();
+        tasks.sort_by_key(|&(id, _)| id);
+        tasks
+    }
+}
+
+impl WakeInterceptor for WakeLatency {
+    fn on_unpark(&self, _: &mut Wake) {}
+
+    fn on_poll(&self, wake: &Wake, delay: Duration) {
+        let mut histograms = self.0.lock().unwrap();
+        histograms.total.record(delay);
+        histograms.tasks.entry(wake.task()).or_default().record(delay);
+    }
+
+    fn on_complete(&self, task: TaskId) {
+        self.0.lock().unwrap().tasks.remove(&task);
+    }
+}
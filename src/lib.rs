@@ -2,6 +2,39 @@
 //!
 //! All tasks are cooperatively run on the same thread and no I/O polling is
 //! done.
+//!
+//! Spawned tasks cannot fail, which is expressed with the uninhabited
+//! [`Void`](enum.Void.html) error type.  It is re-exported here along with
+//! [`ResultVoidExt`](trait.ResultVoidExt.html), so there is no need to depend
+//! on the `void` crate directly.  Futures that use the standard
+//! `Infallible` error type instead can be spawned with
+//! [`Handle::spawn_infallible`](struct.Handle.html#method.spawn_infallible)
+//! and run with
+//! [`Core::run_infallible`](struct.Core.html#method.run_infallible).
+//! Futures given to [`Handle::spawn`](struct.Handle.html#method.spawn) may
+//! fail all the same: their errors are passed to the
+//! [error handler](struct.Core.html#method.on_task_error) of the core.
+//!
+//! The subsystems that run threads of their own or add work to every turn or
+//! poll can be left out of the build by disabling their cargo features, which
+//! are all enabled by default.  They are independent of each other, except
+//! that `latency` needs `intercept`:
+//!
+//! - `box-pool`: reuse of the allocations of large futures, and its
+//!   [statistics](struct.Core.html#method.pool_stats).
+//! - `intercept`: [wake interceptors](trait.WakeInterceptor.html) and
+//!   [wake sources](struct.Core.html#method.record_wake_sources).
+//! - `latency`: [wake latencies](struct.Core.html#method.record_wake_latency)
+//!   and their histograms.
+//! - `lock-stats`: [contention statistics](struct.Core.html#method.lock_stats)
+//!   of the ready queue.
+//! - `sampler`: [sampling](struct.Core.html#method.start_sampler) of the
+//!   queue depth, turn rate and task count.
+//! - `turn-context`: the [turn context](struct.TurnContext.html),
+//!   [`context`](fn.context.html), [`current_task_id`](fn.current_task_id.html)
+//!   and [`Handle::current`](struct.Handle.html#method.current).
+//! - `turn-count`: the [turn counter](struct.Core.html#method.turns).
+//! - `watchdog`: the [watchdog](struct.Core.html#method.set_watchdog) thread.
 
 extern crate futures;
 #[cfg(feature = "futures-spawn")]
@@ -10,20 +43,149 @@ extern crate index_queue;
 extern crate vec_arena;
 extern crate void;
 
+mod abort;
+mod batch;
+mod cancel;
+mod completions;
+#[macro_use]
+mod coop;
+mod core_pool;
 pub mod drop_off;
+mod graph;
+mod idle;
+mod inject;
+#[cfg(feature = "intercept")]
+mod intercept;
+mod join;
+mod join_set;
+#[cfg(feature = "latency")]
+mod latency;
+pub mod loan;
+#[cfg(feature = "lock-stats")]
+mod lock_stats;
+mod pacing;
+mod partition;
+#[cfg(feature = "box-pool")]
+mod pool;
+mod queue;
+mod readiness;
+mod release;
+mod report_error;
+#[cfg(feature = "sampler")]
+mod sampler;
+mod schedule;
+mod scope;
+mod shutdown;
+mod small_box;
+#[cfg(feature = "intercept")]
+mod sources;
+mod spawn_error;
 mod spawn_future;
+mod spawn_when_ready;
+mod stream_fn;
+mod supervise;
+mod task_handle;
+mod tasks;
+#[cfg(feature = "turn-context")]
+mod turn_context;
+mod unordered;
+mod wait_handle;
+mod waker;
+#[cfg(feature = "watchdog")]
+mod watchdog;
 
-pub use spawn_future::SpawnFuture;
+pub use abort::{AbortHandle, AbortRegistration};
+pub use cancel::{CancellationToken, Cancelled};
+pub use completions::{Completions, TaskCompletion, TaskOutcome};
+pub use coop::{Cooperative, StreamExt, coop};
+pub use core_pool::{CorePool, PooledCore};
+pub use graph::{GraphFuture, NodeId, TaskGraph};
+pub use idle::{IdleStats, IdleStrategy};
+pub use inject::Injector;
+#[cfg(feature = "intercept")]
+pub use intercept::{Wake, WakeInterceptor};
+pub use join::{JoinError, JoinHandle};
+pub use join_set::JoinSet;
+#[cfg(feature = "latency")]
+pub use latency::{LatencyHistogram, WakeLatency};
+#[cfg(feature = "lock-stats")]
+pub use lock_stats::LockStats;
+pub use partition::Partition;
+#[cfg(feature = "box-pool")]
+pub use pool::PoolStats;
+pub use queue::{FifoQueue, ReadyQueue};
+pub use readiness::{AllReady, ReadyToken};
+pub use release::ReleaseReport;
+#[cfg(feature = "sampler")]
+pub use sampler::{Sample, Sampler};
+pub use scope::{Scope, ScopeFuture};
+pub use shutdown::{RunOutcome, ShutdownMode};
+#[cfg(feature = "intercept")]
+pub use sources::{WakeSource, WakeSources};
+pub use spawn_error::SpawnError;
+pub use spawn_future::{AnyJoinHandle, SpawnFuture};
+pub use spawn_when_ready::SpawnWhenReady;
+pub use task_handle::TaskHandle;
+pub use tasks::{TaskRef, TaskState};
+pub use stream_fn::{StreamFn, UnfoldLocal, stream_fn, unfold_local};
+pub use supervise::{Restart, RestartPolicy};
+#[cfg(feature = "turn-context")]
+pub use turn_context::{TurnContext, context, current_task_id};
+pub use unordered::LocalFuturesUnordered;
+pub use wait_handle::{Signal, WaitHandle};
+pub use waker::current_waker;
+#[cfg(feature = "watchdog")]
+pub use watchdog::WatchdogReport;
+pub use void::{ResultVoidExt, Void};
+// for `coop!`, so that crates using it need not depend on futures by name
+#[doc(hidden)]
+pub use futures::Async as __Async;
 
-use std::fmt;
+use std::{cmp, fmt, mem};
+use std::any::{self, Any, TypeId};
+use std::borrow::Cow;
+#[cfg(feature = "turn-count")]
+use std::cell::Cell;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::ops::Deref;
 use std::rc::{self, Rc};
+#[cfg(feature = "turn-context")]
+use std::sync;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(debug_assertions)]
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use std::vec;
 use futures::executor::{self, Spawn, Unpark};
-use futures::{Async, Future, Poll, future, task};
+use futures::{Async, Future, IntoFuture, Poll, future, task};
 use index_queue::IndexQueue;
+#[cfg(feature = "sampler")]
+use sampler::Counters;
+use abort::Abortable;
+use completions::Publisher;
+use idle::Idle;
+use schedule::{Schedulable, Schedule};
+#[cfg(feature = "intercept")]
+use intercept::InterceptorSlot;
+#[cfg(feature = "lock-stats")]
+use lock_stats::QueueLock;
+#[cfg(feature = "box-pool")]
+use pool::BoxPool;
+use readiness::Readiness;
+use report_error::ReportError;
+use small_box::SmallBox;
+#[cfg(feature = "turn-context")]
+use turn_context::Deferred;
+// nothing can be asked of a turn context that does not exist
+#[cfg(not(feature = "turn-context"))]
+type Deferred = ();
+use supervise::Supervised;
 use vec_arena::Arena;
-use void::Void;
+#[cfg(feature = "watchdog")]
+use watchdog::Watchdog;
 
 /// Helper struct for writing `Debug` implementations.
 struct DebugWith<F>(F);
@@ -52,7 +214,8 @@ impl SpawnId {
     }
 
     fn aux(aux_index: usize) -> Self {
-        debug_assert!(aux_index < !0 - 1);
+        // the number of slots is bounded by MAX_TASKS
+        debug_assert!(aux_index < MAX_TASKS);
         Self::from_queue_index(aux_index + 1)
     }
 
@@ -65,12 +228,67 @@ impl SpawnId {
     }
 }
 
+/// Identifies a task spawned on a [`Core`](struct.Core.html).
+///
+/// An id pairs the slot the task is stored in with a generation, the number
+/// of tasks the slot held before, so ids are never reused by the same `Core`
+/// even though slots are.  A slot is retired for good once it has held 2^32
+/// tasks, so running out of generations only costs the few bytes of the
+/// retired slot.  What does run out is the slots themselves: a core holds at
+/// most 2^32 - 1 tasks at a time, and
+/// [`Handle::spawn`](struct.Handle.html#method.spawn) fails with
+/// [`SpawnError::IdsExhausted`](enum.SpawnError.html) beyond that.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    /// The id given to the future passed to
+    /// [`run_future`](struct.Core.html#method.run_future).
+    pub fn main() -> Self {
+        TaskId(0)
+    }
+
+    fn new(aux: usize, generation: u32) -> Self {
+        TaskId(u64::from(generation) << 32 | (aux as u64 + 1))
+    }
+
+    /// Index in the arena of the slot the task is stored in, or `None` for
+    /// the main task.
+    fn aux(&self) -> Option<usize> {
+        match self.0 as u32 {
+            0 => None,
+            slot => Some(slot as usize - 1),
+        }
+    }
+
+    /// The number behind the id, e.g. to label logs or metrics.  The low 32
+    /// bits are the slot of the task, counting from 1, and the high 32 bits
+    /// its generation.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The most tasks a core can hold at once, so that the slot of a task fits
+/// in the low half of its id.
+const MAX_TASKS: usize = u32::MAX as usize;
+
+#[cfg(not(feature = "lock-stats"))]
+type QueueLock = Mutex<Box<ReadyQueue>>;
+
+type SharedQueue = Arc<QueueLock>;
+
 // we need atomics here because Unpark requires Send + Sync :/
 struct TicketInner {
     // keep the id out of the 'Option': this helps debuggability (so we know
     // which spawn this ticket belongs to) and also allows null-Arc optimizations
     id: SpawnId,
-    queue: Option<Arc<Mutex<IndexQueue>>>,
+    queue: Option<SharedQueue>,
+    // whether the task has been woken since it was last polled
+    queued: bool,
+    // the first intercepted unpark since the task was last polled
+    #[cfg(feature = "intercept")]
+    pending: Option<(Arc<WakeInterceptor>, Wake)>,
 }
 
 impl fmt::Debug for TicketInner {
@@ -87,214 +305,2197 @@ impl fmt::Debug for TicketInner {
 }
 
 #[derive(Debug)]
-struct Ticket(Mutex<TicketInner>);
+struct Ticket {
+    #[cfg(feature = "intercept")]
+    task: TaskId,
+    #[cfg(feature = "intercept")]
+    interceptor: InterceptorSlot,
+    // the number of queued tasks of the core, unless this is the ticket of
+    // the main future
+    queued: Option<Arc<AtomicUsize>>,
+    // the thread of the core, the only one whose wake-ups are batched
+    owner: Affinity,
+    state: Mutex<TicketInner>,
+}
 
 impl Ticket {
     fn deactivate(&self) {
-        let inner = self.0.lock().unwrap();
-        inner.queue.as_ref().map(|queue| {
-            queue.lock().unwrap().remove(inner.id.to_queue_index());
+        let mut inner = self.state.lock().unwrap();
+        // the slot may be reused, so later unparks must not queue it
+        inner.queue.take().map(|queue| {
+            let index = inner.id.to_queue_index();
+            batch::cancel(&queue, index);
+            queue.lock().unwrap().remove(index);
         });
+        self.set_queued(&mut inner, false);
+    }
+
+    /// Count the task as queued, however it is going to be polled: through
+    /// the ready queue, a batch of deferred wake-ups or `poll_first`.
+    fn mark_queued(&self) {
+        let mut inner = self.state.lock().unwrap();
+        if inner.queue.is_some() {
+            self.set_queued(&mut inner, true);
+        }
+    }
+
+    /// Stop counting the task as queued, since it is being polled.
+    fn unqueue(&self) {
+        self.set_queued(&mut self.state.lock().unwrap(), false);
+    }
+
+    fn is_queued(&self) -> bool {
+        self.state.lock().unwrap().queued
+    }
+
+    fn set_queued(&self, inner: &mut TicketInner, queued: bool) {
+        if inner.queued == queued {
+            return;
+        }
+        inner.queued = queued;
+        if let Some(ref count) = self.queued {
+            if queued {
+                count.fetch_add(1, Ordering::SeqCst);
+            } else {
+                count.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Report the unpark that caused the task to be polled now, if any, to
+    /// the interceptor.
+    #[cfg(feature = "intercept")]
+    fn start_poll(&self) {
+        if !self.interceptor.is_installed() {
+            return;
+        }
+        let pending = self.state.lock().unwrap().pending.take();
+        if let Some((interceptor, wake)) = pending {
+            interceptor.on_poll(&wake, wake.at().elapsed());
+        }
+    }
+
+    /// Report an unpark to the interceptor, if any, and keep the first one
+    /// since the task was last polled for `start_poll`.
+    #[cfg(feature = "intercept")]
+    fn intercept(&self) {
+        let interceptor = match self.interceptor.get() {
+            Some(interceptor) => interceptor,
+            None => return,
+        };
+        if self.state.lock().unwrap().queue.is_none() {
+            return;
+        }
+        // the interceptor is called without the lock, so that it may unpark
+        // tasks itself
+        let mut wake = Wake::new(self.task);
+        if !intercept::on_unpark(&*interceptor, &mut wake) {
+            return;
+        }
+        let mut inner = self.state.lock().unwrap();
+        if inner.pending.is_none() && inner.queue.is_some() {
+            inner.pending = Some((interceptor, wake));
+        }
     }
 }
 
 impl Unpark for Ticket {
     fn unpark(&self) {
-        let inner = self.0.lock().unwrap();
-        inner.queue.as_ref().map(|queue| {
-            queue.lock().unwrap().push_back(inner.id.to_queue_index());
-        });
+        #[cfg(feature = "intercept")]
+        self.intercept();
+        let mut inner = self.state.lock().unwrap();
+        let queue = match inner.queue {
+            Some(ref queue) => queue.clone(),
+            None => return,
+        };
+        let index = inner.id.to_queue_index();
+        if batch::defer(&queue, index) {
+            // the batch skips the lock of the queue until the poll returns
+            self.owner.check("Task");
+        } else {
+            queue.lock().unwrap().push_back(index);
+        }
+        self.set_queued(&mut inner, true);
+    }
+}
+
+/// The ticket of a task, deactivated once the task is dropped however that
+/// happens, so that its slot can be reused safely.
+#[derive(Debug)]
+struct TaskTicket(Arc<Ticket>);
+
+impl Deref for TaskTicket {
+    type Target = Arc<Ticket>;
+    fn deref(&self) -> &Arc<Ticket> {
+        &self.0
+    }
+}
+
+impl Drop for TaskTicket {
+    fn drop(&mut self) {
+        self.0.deactivate();
     }
 }
 
 struct Spawned<F> {
     spawn: Spawn<F>,
-    ticket: Arc<Ticket>,
+    ticket: TaskTicket,
+    id: TaskId,
+    // the number of tasks spawned before it, modulo 2^64
+    order: u64,
+    name: Option<Cow<'static, str>>,
+    spawned_at: Instant,
+    polls: u64,
+    // size of the future itself, not counting anything it points to
+    size: usize,
+    // whether Core::run_until_done may drop the task unfinished
+    daemon: bool,
 }
 
 impl<F> fmt::Debug for Spawned<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_tuple("Spawned")
+            .field(&self.id)
+            .field(&self.name)
             .field(&self.ticket)
+            .field(&self.size)
             .finish()
     }
 }
 
-type SpawnedBox<'a> = Spawned<Box<Future<Item=(), Error=Void> + 'a>>;
+type SpawnedBox<'a> = Spawned<SmallBox<'a>>;
+
+type SizeWarning<'a> = Box<FnMut(usize) + 'a>;
+
+type AbandonedHook<'a> = Box<FnMut(TaskId) + 'a>;
+
+type SpawnHook<'a> = Box<FnMut(&SpawnRecord) + 'a>;
+
+type ErrorHook<'a> = Box<FnMut(TaskId, &fmt::Debug) + 'a>;
+
+type ShutdownHook<'a> =
+    Box<FnOnce() -> Box<Future<Item=(), Error=Void> + 'a> + 'a>;
+
+/// Information about a task that is being spawned, passed to the hook
+/// registered with [`Core::on_spawn`](struct.Core.html#method.on_spawn).
+#[derive(Clone, Debug)]
+pub struct SpawnRecord {
+    /// When the task was spawned.
+    pub at: SystemTime,
+    /// Id of the new task.
+    pub id: TaskId,
+    /// The task of the same core that spawned it, if any.
+    pub parent: Option<TaskId>,
+    /// Type of the future, or of the box it was spawned in.
+    pub type_name: &'static str,
+    /// Name given with
+    /// [`Handle::spawn_named`](struct.Handle.html#method.spawn_named), if
+    /// any.
+    pub name: Option<Cow<'static, str>>,
+}
 
-#[derive(Default)]
 struct Inner<'a> {
     spawns: Arena<Option<SpawnedBox<'a>>>,
-    queue: Arc<Mutex<IndexQueue>>,
+    // the thread that created the core, which is the only one to use it
+    owner: Affinity,
+    queue: SharedQueue,
+    // the number of tasks woken and not polled since, wherever they wait
+    queued: Arc<AtomicUsize>,
+    injectors: Vec<Injector>,
+    #[cfg(feature = "sampler")]
+    counters: Option<Arc<Counters>>,
+    large_future: Option<(usize, SizeWarning<'a>)>,
+    // the number of tasks beyond which spawn refuses more
+    max_tasks: Option<usize>,
+    // tasks waiting for the number of tasks to drop below the limit
+    capacity_waiters: Vec<task::Task>,
+    // tasks to poll at the start of the next turn
+    poll_first: Vec<TaskId>,
+    // made current while the tasks are polled
+    #[cfg(feature = "turn-context")]
+    turn_context: Rc<TurnContext>,
+    abandoned: Option<AbandonedHook<'a>>,
+    spawn_hook: Option<SpawnHook<'a>>,
+    shutdown_hooks: Vec<ShutdownHook<'a>>,
+    error_hook: Option<ErrorHook<'a>>,
+    // the task being polled
+    current: Option<TaskId>,
+    // the ticket of the future passed to run_future, for TurnContext::wake
+    #[cfg(feature = "turn-context")]
+    main: sync::Weak<Ticket>,
+    // whether the task being polled reported an error
+    failed: bool,
+    schedule: Schedule,
+    completions: Publisher,
+    #[cfg(feature = "intercept")]
+    interceptor: InterceptorSlot,
+    // a Vec<T> for each type T of results collected so far
+    results: HashMap<TypeId, Box<Any>>,
+    #[cfg(feature = "box-pool")]
+    pool: Rc<BoxPool>,
+    readiness: Readiness,
+    // the generation of each slot of the arena, for the ids of its tasks
+    generations: Vec<u32>,
+    // slots whose generations have run out, left occupied so that they are
+    // not reused
+    retired: usize,
+    // the number of tasks spawned so far, which orders them
+    spawned: u64,
+    // the number of unfinished tasks that are not daemons
+    foreground: usize,
+    // bumped when a pooled core is handed out again, so that the handles of
+    // its previous user stop working
+    generation: u64,
+    created: Instant,
+    // a cell, so that a turn need not borrow the core mutably to count it
+    #[cfg(feature = "turn-count")]
+    turns: Cell<u64>,
+    completed: u64,
+}
+
+impl<'a> Default for Inner<'a> {
+    fn default() -> Self {
+        Self::with_queue(Box::new(IndexQueue::default()))
+    }
 }
 
 impl<'a> Inner<'a> {
-    fn new_ticket(&self, id: SpawnId) -> Arc<Ticket> {
-        let ticket = Arc::new(Ticket(Mutex::new(TicketInner {
-            id: id,
-            queue: Some(self.queue.clone()),
-        })));
+    fn with_queue(queue: Box<ReadyQueue>) -> Self {
+        Inner {
+            spawns: Default::default(),
+            owner: Affinity::current(),
+            queue: Arc::new(QueueLock::new(queue)),
+            queued: Arc::new(AtomicUsize::new(0)),
+            injectors: Vec::new(),
+            #[cfg(feature = "sampler")]
+            counters: None,
+            large_future: None,
+            max_tasks: None,
+            capacity_waiters: Vec::new(),
+            poll_first: Vec::new(),
+            #[cfg(feature = "turn-context")]
+            turn_context: Rc::new(TurnContext::default()),
+            abandoned: None,
+            spawn_hook: None,
+            shutdown_hooks: Vec::new(),
+            error_hook: None,
+            current: None,
+            #[cfg(feature = "turn-context")]
+            main: sync::Weak::new(),
+            failed: false,
+            schedule: Schedule::default(),
+            completions: Publisher::default(),
+            #[cfg(feature = "intercept")]
+            interceptor: InterceptorSlot::default(),
+            results: HashMap::new(),
+            #[cfg(feature = "box-pool")]
+            pool: Rc::new(BoxPool::default()),
+            readiness: Readiness::default(),
+            generations: Vec::new(),
+            retired: 0,
+            spawned: 0,
+            foreground: 0,
+            generation: 0,
+            created: Instant::now(),
+            #[cfg(feature = "turn-count")]
+            turns: Cell::new(0),
+            completed: 0,
+        }
+    }
+
+    #[cfg_attr(not(feature = "intercept"), allow(unused_variables))]
+    fn new_parked_ticket(&self, id: SpawnId, task: TaskId) -> Arc<Ticket> {
+        Arc::new(Ticket {
+            #[cfg(feature = "intercept")]
+            task: task,
+            #[cfg(feature = "intercept")]
+            interceptor: self.interceptor.clone(),
+            queued: id.to_aux().map(|_| self.queued.clone()),
+            owner: self.owner,
+            state: Mutex::new(TicketInner {
+                id: id,
+                queue: Some(self.queue.clone()),
+                queued: false,
+                #[cfg(feature = "intercept")]
+                pending: None,
+            }),
+        })
+    }
+
+    fn new_ticket(&self, id: SpawnId, task: TaskId) -> Arc<Ticket> {
+        let ticket = self.new_parked_ticket(id, task);
         ticket.unpark();
         ticket
     }
+
+    /// Index in the arena of the unfinished task with the given id.  The
+    /// task being polled is not found.
+    fn find(&self, id: TaskId) -> Option<usize> {
+        let aux = id.aux()?;
+        match self.spawns.get(aux) {
+            Some(&Some(ref spawned)) if spawned.id == id => Some(aux),
+            _ => None,
+        }
+    }
+
+    /// Pass the error of the task being polled to the error hook, if any.
+    fn report_error(&mut self, err: &fmt::Debug) {
+        let task = self.current.unwrap_or_else(TaskId::main);
+        self.failed = true;
+        if let Some(ref mut hook) = self.error_hook {
+            hook(task, err);
+        }
+    }
+
+    /// Store a future to be spawned, taking its allocation from the pool if
+    /// it is too large to be stored inline.
+    #[cfg(feature = "box-pool")]
+    fn small_box<F>(&self, f: F) -> SmallBox<'a>
+        where F: Future<Item=(), Error=Void> + 'a
+    {
+        SmallBox::new_in(f, &self.pool)
+    }
+
+    /// Store a future to be spawned, boxing it if it is too large to be
+    /// stored inline.
+    #[cfg(not(feature = "box-pool"))]
+    fn small_box<F>(&self, f: F) -> SmallBox<'a>
+        where F: Future<Item=(), Error=Void> + 'a
+    {
+        SmallBox::new(f)
+    }
+
+    /// Add a spawn without queuing it.  Returns its aux index, or `None` if
+    /// the ids are exhausted, in which case the future is dropped.
+    fn insert(&mut self, f: SmallBox<'a>, type_name: &'static str)
+              -> Option<usize> {
+        self.insert_named(f, type_name, None)
+    }
+
+    fn insert_named(&mut self,
+                    f: SmallBox<'a>,
+                    type_name: &'static str,
+                    name: Option<Cow<'static, str>>) -> Option<usize> {
+        if self.ids_exhausted() {
+            return None;
+        }
+        let size = f.size();
+        if let Some((threshold, ref mut warn)) = self.large_future {
+            if size > threshold {
+                warn(size);
+            }
+        }
+        let aux = self.spawns.insert(None);
+        if aux == self.generations.len() {
+            self.generations.push(0);
+        }
+        let id = TaskId::new(aux, self.generations[aux]);
+        self.spawned = self.spawned.wrapping_add(1);
+        self.foreground += 1;
+        if let Some(ref mut hook) = self.spawn_hook {
+            hook(&SpawnRecord {
+                at: SystemTime::now(),
+                id: id,
+                parent: self.current,
+                type_name: type_name,
+                name: name.clone(),
+            });
+        }
+        let ticket = self.new_parked_ticket(SpawnId::aux(aux), id);
+        self.spawns[aux] = Some(Spawned {
+            spawn: executor::spawn(f),
+            ticket: TaskTicket(ticket),
+            id: id,
+            order: self.spawned,
+            name: name,
+            spawned_at: Instant::now(),
+            polls: 0,
+            size: size,
+            daemon: false,
+        });
+        Some(aux)
+    }
+
+    /// Insert a task without queuing it, and return its id.
+    fn insert_parked(&mut self, f: SmallBox<'a>, type_name: &'static str)
+                     -> Option<TaskId> {
+        let aux = self.insert(f, type_name)?;
+        Some(self.spawns[aux].as_ref().unwrap().id)
+    }
+
+    fn spawn(&mut self, f: SmallBox<'a>, type_name: &'static str)
+             -> Option<TaskId> {
+        let aux = self.insert(f, type_name)?;
+        let spawned = self.spawns[aux].as_ref().unwrap();
+        spawned.ticket.unpark();
+        Some(spawned.id)
+    }
+
+    /// Free the slot at `aux`, and return its task unless it is being
+    /// polled, in which case the caller accounts for it with
+    /// [`finished`](#method.finished).  A slot whose generations have run
+    /// out is retired instead.
+    fn remove(&mut self, aux: usize) -> Option<SpawnedBox<'a>> {
+        let spawned = if self.generations[aux] == u32::MAX {
+            self.retired += 1;
+            self.spawns[aux].take()
+        } else {
+            self.generations[aux] += 1;
+            self.spawns.remove(aux).and_then(|spawned| spawned)
+        };
+        if let Some(ref spawned) = spawned {
+            self.finished(spawned);
+        }
+        spawned
+    }
+
+    /// Account for a task that has been removed.
+    fn finished(&mut self, spawned: &SpawnedBox<'a>) {
+        if !spawned.daemon {
+            self.foreground -= 1;
+        }
+    }
+
+    /// Pop the next spawn off the ready queue, recording the depth of the
+    /// queue for the sampler.
+    fn pop_ready(&self) -> Option<usize> {
+        let mut queue = self.queue.lock().unwrap();
+        let popped = queue.pop_front();
+        #[cfg(feature = "sampler")]
+        if let Some(ref counters) = self.counters {
+            counters.record(queue.len(), self.len());
+        }
+        popped
+    }
+
+    /// Number of tasks that have not finished.
+    fn len(&self) -> usize {
+        self.spawns.len() - self.retired
+    }
+
+    /// Whether there is no slot left for a new task.  The slots in use,
+    /// retired ones included, are the only ones below the limit once there
+    /// are as many of them as the limit.
+    fn ids_exhausted(&self) -> bool {
+        self.spawns.len() >= MAX_TASKS
+    }
+
+    /// Number of tasks that can still be spawned, as far as both the task
+    /// limit and the ids allow.
+    fn room(&self) -> usize {
+        let ids = MAX_TASKS - self.spawns.len();
+        match self.max_tasks {
+            Some(max) => cmp::min(ids, max.saturating_sub(self.len())),
+            None => ids,
+        }
+    }
+
+    /// Whether the task limit has been reached.
+    fn is_full(&self) -> bool {
+        match self.max_tasks {
+            Some(max) => self.len() >= max,
+            None => false,
+        }
+    }
+
+    /// The tasks that have not finished, in the order they are to be
+    /// dropped: newest first, so that a task is dropped before the tasks
+    /// that were spawned ahead of it (such as the one that spawned it).
+    fn remaining(&self) -> Vec<(TaskId, usize)> {
+        let mut remaining: Vec<_> = self.spawns.iter()
+            .filter_map(|(aux, spawned)| {
+                spawned.as_ref().map(|spawned| (spawned.order, spawned.id, aux))
+            })
+            .collect();
+        remaining.sort_by(|a, b| b.cmp(a));
+        remaining.into_iter().map(|(_, id, aux)| (id, aux)).collect()
+    }
+}
+
+impl<'a> Drop for Inner<'a> {
+    fn drop(&mut self) {
+        for (id, aux) in self.remaining() {
+            if let Some(ref mut abandoned) = self.abandoned {
+                abandoned(id);
+            }
+            if let Some(spawned) = self.remove(aux) {
+                self.completions.publish(spawned.id,
+                                         &spawned.name,
+                                         TaskOutcome::Dropped,
+                                         spawned.spawned_at.elapsed());
+            }
+        }
+        // so that they fail instead of waiting forever
+        for waiter in self.capacity_waiters.drain(..) {
+            waiter.unpark();
+        }
+    }
 }
 
 impl<'a> fmt::Debug for Inner<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("Inner")
+        let mut debug = f.debug_struct("Inner");
+        debug
             .field("spawns", &DebugWith(|f: &mut fmt::Formatter| {
-                f.debug_list().entries(self.spawns.iter().map(|(i, _)| i))
+                f.debug_map()
+                    .entries(self.spawns.iter()
+                             .filter_map(|(_, spawned)| spawned.as_ref())
+                             .map(|spawned| (spawned.id, &spawned.name)))
                     .finish()
             }))
             .field("queue", &self.queue)
+            .field("injectors", &self.injectors);
+        #[cfg(feature = "sampler")]
+        debug.field("counters", &self.counters);
+        debug
+            .field("large_future",
+                   &self.large_future.as_ref().map(|&(size, _)| size))
+            .field("max_tasks", &self.max_tasks)
+            .field("capacity_waiters", &self.capacity_waiters.len())
+            .field("poll_first", &self.poll_first);
+        #[cfg(feature = "turn-context")]
+        debug.field("turn_context", &self.turn_context);
+        debug
+            .field("abandoned", &self.abandoned.is_some())
+            .field("spawn_hook", &self.spawn_hook.is_some())
+            .field("shutdown_hooks", &self.shutdown_hooks.len())
+            .field("current", &self.current)
+            .field("schedule", &self.schedule)
+            .field("completions", &self.completions);
+        #[cfg(feature = "intercept")]
+        debug.field("interceptor", &self.interceptor);
+        debug
+            .field("results", &self.results.len())
+            .field("readiness", &self.readiness)
+            .field("retired", &self.retired)
+            .field("spawned", &self.spawned);
+        #[cfg(feature = "turn-count")]
+        debug.field("turns", &self.turns.get());
+        debug
+            .field("completed", &self.completed)
             .finish()
     }
 }
 
+/// The thread that created a `Core` or `Handle`, tracked only in debug
+/// builds.
+#[derive(Clone, Copy, Debug)]
+struct Affinity(#[cfg(debug_assertions)] thread::ThreadId);
+
+impl Affinity {
+    #[cfg(debug_assertions)]
+    fn current() -> Self {
+        Affinity(thread::current().id())
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn current() -> Self {
+        Affinity()
+    }
+
+    #[cfg(debug_assertions)]
+    fn check(self, what: &str) {
+        assert!(self.0 == thread::current().id(),
+                "synchrotron::{} used from a thread other than the one that \
+                 owns its Core", what);
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn check(self, _: &str) {}
+}
+
 /// A cloneable handle to a [`Core`](struct.Core.html).
 ///
 /// Cloned handles always refer to the same `Core` instance.
 ///
 /// `Handle` can be used to `spawn` tasks even when the `Core` is running.
+///
+/// A `Handle` must only be used on the thread of its `Core`.  In debug
+/// builds this is checked, and violations panic.
+///
+/// A handle to a core taken out of a [`CorePool`](struct.CorePool.html)
+/// stops working once the core is given back, as if the core were dropped.
 #[derive(Debug, Clone)]
-pub struct Handle<'a>(rc::Weak<RefCell<Inner<'a>>>);
+pub struct Handle<'a>(rc::Weak<RefCell<Inner<'a>>>, Affinity, u64);
 
 impl<'a> Handle<'a> {
+    fn upgrade(&self) -> Option<Rc<RefCell<Inner<'a>>>> {
+        self.1.check("Handle");
+        self.live()
+    }
+
+    // the core, unless it is gone or was handed out again since
+    fn live(&self) -> Option<Rc<RefCell<Inner<'a>>>> {
+        self.0.upgrade()
+            .filter(|inner| inner.borrow().generation == self.2)
+    }
+
+    /// Spawn a new task with a name, which shows in the `Debug` output of
+    /// the core and in [`Core::tasks`](struct.Core.html#method.tasks).
+    pub fn spawn_named<N, F>(&self, name: N, f: F)
+        where N: Into<Cow<'static, str>>,
+              F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        if let Some(inner) = self.upgrade() {
+            let mut inner = inner.borrow_mut();
+            let f = inner.small_box(f.into_future());
+            let aux = inner.insert_named(f,
+                                         any::type_name::<F::Future>(),
+                                         Some(name.into()));
+            if let Some(aux) = aux {
+                inner.spawns[aux].as_ref().unwrap().ticket.unpark();
+            }
+        }
+    }
+
+    /// Spawn a background task, which
+    /// [`Core::run_until_done`](struct.Core.html#method.run_until_done)
+    /// does not wait for: it is dropped once the other tasks are done.
+    pub fn spawn_daemon<F>(&self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        if let Some(inner) = self.upgrade() {
+            let mut inner = inner.borrow_mut();
+            let f = inner.small_box(f.into_future());
+            if let Some(aux) = inner.insert(f, any::type_name::<F::Future>()) {
+                inner.foreground -= 1;
+                let spawned = inner.spawns[aux].as_mut().unwrap();
+                spawned.daemon = true;
+                spawned.ticket.unpark();
+            }
+        }
+    }
+
+    /// A handle to the core that is polling the current task, so that code
+    /// deep inside a future can spawn tasks without being handed a handle.
+    ///
+    /// Returns `None` outside of a poll by a core, or if the core has not
+    /// [enabled](struct.Core.html#method.enable_current_handle) it.
+    #[cfg(feature = "turn-context")]
+    pub fn current() -> Option<OwnedHandle> {
+        turn_context::with(TurnContext::handle).and_then(|handle| handle)
+    }
+
     /// Spawn a new task into the executor.  The spawned tasks are executed
     /// when [`run`](struct.Core.html#method.run) is called.
-    pub fn spawn<F: Future<Item=(), Error=Void> + 'a>(&self, f: F) {
-        let inner = match self.0.upgrade() {
+    ///
+    /// Anything that converts into a future can be spawned, including plain
+    /// `Result`s.  The future is given back if the core has been dropped or
+    /// has as many tasks as
+    /// [`Core::set_max_tasks`](struct.Core.html#method.set_max_tasks)
+    /// allows.
+    ///
+    /// If the future fails, its error is passed to the handler set with
+    /// [`Core::on_task_error`](struct.Core.html#method.on_task_error), or
+    /// dropped if there is none.  Futures that cannot fail use the
+    /// [`Void`](enum.Void.html) error type, which has to be spelled out for
+    /// futures like `future::empty::<(), Void>()`.
+    pub fn spawn<F>(&self, f: F) -> Result<(), SpawnError<F>>
+        where F: IntoFuture<Item=()>, F::Future: 'a, F::Error: fmt::Debug + 'a
+    {
+        let inner = match self.upgrade() {
             Some(inner) => inner,
-            None => return,
+            None => return Err(SpawnError::Shutdown(f)),
         };
         let mut inner = inner.borrow_mut();
-        let aux = inner.spawns.insert(None);
-        let ticket = inner.new_ticket(SpawnId::aux(aux));
-        inner.spawns[aux] = Some(Spawned {
-            spawn: executor::spawn(Box::new(f) as Box<_>),
-            ticket: ticket,
-        });
+        inner.owner.check("Handle");
+        if inner.is_full() {
+            return Err(SpawnError::Full(f));
+        }
+        if inner.ids_exhausted() {
+            return Err(SpawnError::IdsExhausted(f));
+        }
+        let f = ReportError::new(f.into_future(), self.0.clone());
+        let f = inner.small_box(f);
+        inner.spawn(f, any::type_name::<F::Future>());
+        Ok(())
     }
 
-    /// Spawn a future as its own task and then return a future that can be
-    /// used to query its result.
-    pub fn spawn_future<F: Future>(&self, future: F) -> SpawnFuture<'a, F> {
-        SpawnFuture::new(self.clone(), future)
+    /// Spawn a new task whatever the task limit, or drop the future if the
+    /// core no longer exists.  This is for the ways to spawn that have no
+    /// way to hand the future back.
+    pub(crate) fn spawn_or_drop<F>(&self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        if let Some(inner) = self.upgrade() {
+            let mut inner = inner.borrow_mut();
+            let f = inner.small_box(f.into_future());
+            inner.spawn(f, any::type_name::<F::Future>());
+        }
     }
-}
 
-#[cfg(feature = "futures-spawn")]
-impl<'a, F> futures_spawn::Spawn<F> for Handle<'a>
-    where F: Future<Item=(), Error=()> + 'a
-{
-    fn spawn_detached(&self, f: F) {
-        self.spawn(f.or_else(|_| Ok(())))
+    /// Spawn a new task that is polled at the start of the next turn, ahead
+    /// of the tasks waiting in the ready queue, to shorten the time until
+    /// its first poll.
+    ///
+    /// Outside of a turn,
+    /// [`Core::spawn_and_poll_now`](struct.Core.html#method.spawn_and_poll_now)
+    /// polls it right away instead.
+    pub fn spawn_and_poll_now<F>(&self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        if let Some(inner) = self.upgrade() {
+            let mut inner = inner.borrow_mut();
+            let f = inner.small_box(f.into_future());
+            if let Some(aux) = inner.insert(f, any::type_name::<F::Future>()) {
+                let spawned = inner.spawns[aux].as_ref().unwrap();
+                spawned.ticket.mark_queued();
+                let id = spawned.id;
+                inner.poll_first.push(id);
+            }
+        }
     }
-}
 
-/// Unpark the current task if the `status` is `Some(Ok(NotReady))` or `None`.
-fn yield_turn<T, E>(status: Option<Poll<T, E>>) -> Poll<T, E> {
-    let result = status.unwrap_or(Ok(Async::NotReady));
-    if let Ok(Async::NotReady) = result {
-        task::park().unpark();
+    /// Return a future that spawns `f` as soon as the core has fewer tasks
+    /// than [`Core::set_max_tasks`](struct.Core.html#method.set_max_tasks)
+    /// allows, so that a producer only gets ahead of the core as far as the
+    /// limit.  Without a limit, `f` is spawned on the first poll.
+    pub fn spawn_when_ready<F>(&self, f: F) -> SpawnWhenReady<'a, F>
+        where F: IntoFuture<Item=()>, F::Future: 'a, F::Error: fmt::Debug + 'a
+    {
+        SpawnWhenReady::new(self.clone(), f)
     }
-    result
-}
-
-/// A combined `Core` and future `F` that can be run.
-#[derive(Debug)]
-#[must_use = "futures do nothing unless polled"]
-pub struct RunFuture<'b, 'a: 'b, F> {
-    core: &'b mut Core<'a>,
-    spawned: Spawned<F>,
-}
 
-impl<'b, 'a, F: Future> RunFuture<'b, 'a, F> {
-    /// Run the future `F` on the current thread until completion.  Spawned
-    /// tasks are run concurrently as well, but may or may not complete.
-    pub fn run(&mut self) -> Result<F::Item, F::Error> {
-        loop {
-            match self.turn().unwrap_or(Ok(Async::NotReady))? {
-                Async::Ready(x) => return Ok(x),
-                Async::NotReady => continue,
+    /// Unpark the current task once a spawned task finishes.
+    fn wait_for_capacity(&self) {
+        if let Some(inner) = self.upgrade() {
+            let mut inner = inner.borrow_mut();
+            let waiters = &mut inner.capacity_waiters;
+            if !waiters.iter().any(|waiter| waiter.will_notify_current()) {
+                waiters.push(task::park());
             }
         }
     }
 
-    /// Perform one iteration of the executor loop.  Returns `None` if all
-    /// tasks are parked (no apparent progress was made).
-    pub fn turn(&mut self) -> Option<Poll<F::Item, F::Error>> {
-        self.core.turn_with(Ok(&mut self.spawned))
+    /// Like [`spawn`](#method.spawn), but for futures whose error type is
+    /// the standard `Infallible` rather than `Void`, so that they need not
+    /// depend on the `void` crate.
+    pub fn spawn_infallible<F>(&self, f: F)
+        where F: IntoFuture<Item=(), Error=Infallible>, F::Future: 'a
+    {
+        self.spawn_or_drop(f.into_future().map_err(|err| match err {}))
     }
-}
 
-impl<'b, 'a, F: Future> Future for RunFuture<'b, 'a, F> {
-    type Item = F::Item;
-    type Error = F::Error;
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        yield_turn(self.turn())
+    /// Spawn the future returned by `f`, like `futures::future::lazy`: `f` is
+    /// called the first time the task is polled.
+    pub fn spawn_fn<F, R>(&self, f: F)
+        where F: FnOnce() -> R + 'a,
+              R: IntoFuture<Item=(), Error=Void> + 'a
+    {
+        self.spawn_or_drop(future::lazy(f));
     }
-}
 
-/// The task executor.
-#[derive(Debug, Default)]
-pub struct Core<'a>(Rc<RefCell<Inner<'a>>>);
+    /// Spawn the future returned by `f`, which is given a handle to this
+    /// core to spawn more tasks with.
+    ///
+    /// `f` is called the first time the task is polled.
+    pub fn spawn_with_handle<F, R>(&self, f: F)
+        where F: FnOnce(Handle<'a>) -> R + 'a,
+              R: IntoFuture<Item=(), Error=Void> + 'a,
+              R::Future: 'a
+    {
+        let handle = self.clone();
+        self.spawn_fn(move || f(handle));
+    }
 
-impl<'a> Core<'a> {
-    /// Create a [`Handle`](struct.Handle.html) to this executor, which can be
-    /// used to [`spawn`](struct.Handle.html#method.spawn) additional tasks.
-    pub fn handle(&self) -> Handle<'a> {
-        Handle(Rc::downgrade(&self.0))
+    /// Spawn a future whose item is kept by the executor once it completes.
+    ///
+    /// The items can be retrieved after running the core with
+    /// [`take_results`](struct.Core.html#method.take_results), which saves
+    /// setting up a channel for each task in fork-join style jobs.
+    pub fn spawn_collect<F>(&self, f: F)
+        where F: IntoFuture<Error=Void>, F::Item: 'static, F::Future: 'a
+    {
+        let inner = self.0.clone();
+        self.spawn_or_drop(f.into_future().map(move |item| {
+            if let Some(inner) = inner.upgrade() {
+                inner.borrow_mut().results.entry(TypeId::of::<F::Item>())
+                    .or_insert_with(|| Box::new(Vec::<F::Item>::new()))
+                    .downcast_mut::<Vec<F::Item>>()
+                    .expect("results of the wrong type")
+                    .push(item);
+            }
+        }))
     }
 
-    /// Run the given future on the current thread until completion.  Spawned
-    /// tasks are run concurrently as well, but may or may not complete.
+    /// Spawn a future that stores its item in `slot` once it completes.
     ///
-    /// This is equivalent to `self.run_future().run()`.
-    pub fn run<F: Future>(&mut self, f: F) -> Result<F::Item, F::Error> {
-        self.run_future(f).run()
+    /// Unlike [`spawn_future`](#method.spawn_future), this does not
+    /// allocate anything to hand the item over, so it is suited to firing off
+    /// many small tasks whose items are collected afterwards.  Any item
+    /// already in the slot is replaced.
+    pub fn spawn_into<F>(&self, slot: &'a RefCell<Option<F::Item>>, f: F)
+        where F: IntoFuture<Error=Void>, F::Future: 'a
+    {
+        self.spawn_or_drop(f.into_future().map(move |item| {
+            *slot.borrow_mut() = Some(item);
+        }))
     }
 
-    /// Like [`run`](#method.run), but creates a
-    /// [`RunFuture`](struct.RunFuture.html) object, which allows one to
-    /// manually [`turn`](struct.RunFuture.html#method.turn) the executor.
-    pub fn run_future<'b, F: Future>(&'b mut self, f: F)
-                                     -> RunFuture<'b, 'a, F> {
-        let ticket = {
-            let inner = self.0.borrow();
-            // if the main spawn is still queued somehow (because the user did
-            // not complete a previous RunFuture), remove it
-            let id = SpawnId::main();
-            inner.queue.lock().unwrap().remove(id.to_queue_index());
-            inner.new_ticket(id)
+    /// Spawn every future yielded by `futures`.
+    ///
+    /// This is cheaper than calling [`spawn`](#method.spawn) for each one, as
+    /// the tasks are all inserted under a single borrow of the executor and
+    /// queued under a single lock.  The iterator is consumed first, so it may
+    /// spawn tasks itself.
+    ///
+    /// Like `spawn`, this respects the
+    /// [task limit](struct.Core.html#method.set_max_tasks): the futures that
+    /// do not fit, or all of them if the core no longer exists, are handed
+    /// back in the error.
+    pub fn spawn_all<I>(&self, futures: I)
+                        -> Result<(), SpawnError<Vec<I::Item>>>
+        where I: IntoIterator,
+              I::Item: IntoFuture<Item=(), Error=Void>,
+              <I::Item as IntoFuture>::Future: 'a
+    {
+        let mut futures: Vec<_> = futures.into_iter().collect();
+        let inner = match self.upgrade() {
+            Some(inner) => inner,
+            None => return Err(SpawnError::Shutdown(futures)),
         };
-        RunFuture {
-            core: self,
-            spawned: Spawned {
-                spawn: executor::spawn(f),
-                ticket: ticket,
-            },
-        }
+        let (tickets, queue, result) = {
+            let mut inner = inner.borrow_mut();
+            let room = inner.room();
+            let rest = if futures.len() > room {
+                futures.split_off(room)
+            } else {
+                Vec::new()
+            };
+            inner.spawns.reserve(futures.len());
+            let type_name = any::type_name::<<I::Item as IntoFuture>::Future>();
+            let tickets: Vec<_> = futures.into_iter().filter_map(|f| {
+                let f = inner.small_box(f.into_future());
+                let aux = inner.insert(f, type_name)?;
+                Some(inner.spawns[aux].as_ref().unwrap().ticket.clone())
+            }).collect();
+            let result = if rest.is_empty() {
+                Ok(())
+            } else if inner.is_full() {
+                Err(SpawnError::Full(rest))
+            } else {
+                Err(SpawnError::IdsExhausted(rest))
+            };
+            (tickets, inner.queue.clone(), result)
+        };
+        // through the interceptors and the ready count, like any wake-up
+        batch::poll_batched(&queue, || {
+            for ticket in tickets {
+                ticket.unpark();
+            }
+        });
+        result
     }
 
-    /// Perform one iteration of the executor loop.  Returns `None` if all
-    /// tasks are parked (no apparent progress was made).  Returns
-    /// `Some(Ok(Ready(())))` if all spawned tasks have completed.
-    pub fn turn<T>(&mut self) -> Option<Poll<(), T>> {
-        self.turn_with::<future::Empty<(), T>>(Err(()))
+    /// Like [`spawn`](#method.spawn), but for a future that is already
+    /// boxed.  The box is stored as is rather than being boxed again.
+    pub fn spawn_boxed(&self, f: Box<Future<Item=(), Error=Void> + 'a>) {
+        let inner = match self.upgrade() {
+            Some(inner) => inner,
+            None => return,
+        };
+        let type_name =
+            any::type_name::<Box<Future<Item=(), Error=Void> + 'a>>();
+        inner.borrow_mut().spawn(SmallBox::from_box(f), type_name);
     }
 
-    /// Perform one iteration of the executor loop, optionally with a given
-    /// main spawn.  Returns `None` if all tasks are parked (no apparent
-    /// progress could be made).  If `main` is set to `Err(e)`, returns
-    /// `Some(Ok(Ready(e)))` if there are no more spawns.
-    fn turn_with<F: Future>(&mut self, main: Result<&mut Spawned<F>, F::Item>)
-                            -> Option<Poll<F::Item, F::Error>> {
-        let index = {
+    /// Spawn a future that can be aborted with the
+    /// [`AbortHandle`](struct.AbortHandle.html) paired with `registration`.
+    ///
+    /// Once aborted, the task is removed from the core the next time it is
+    /// polled, without polling the future again.
+    pub fn spawn_abortable<F>(&self, f: F, registration: AbortRegistration)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        self.spawn_or_drop(Abortable::new(f.into_future(), registration));
+    }
+
+    /// Spawn a future that is dropped as soon as `token` is cancelled, if
+    /// it has not finished by then.
+    pub fn spawn_cancellable<F>(&self, f: F, token: &CancellationToken)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        // check the token first, so that a cancelled task is never polled
+        self.spawn_or_drop(token.cancelled()
+                           .select(f.into_future())
+                           .then(|_| Ok(())));
+    }
+
+    /// Spawn the future made by `factory`, and spawn a new one each time it
+    /// finishes, as `policy` allows.
+    ///
+    /// The returned handle resolves to the output of the last future, once
+    /// the policy does not allow any more restarts.
+    pub fn supervise<M, R>(&self, policy: RestartPolicy, factory: M)
+                           -> JoinHandle<R::Item, R::Error>
+        where M: FnMut() -> R + 'a, R: IntoFuture + 'a, R::Future: 'a
+    {
+        self.spawn_handle(Supervised::new(policy, factory))
+    }
+
+    /// Create an empty [`JoinSet`](struct.JoinSet.html) that spawns its
+    /// tasks on this core.
+    pub fn join_set<T, E>(&self) -> JoinSet<'a, T, E> {
+        JoinSet::new(self.clone())
+    }
+
+    /// Run `body` with a [`Scope`](struct.Scope.html) to spawn child tasks
+    /// on, and return a future that resolves to the output of the future
+    /// returned by `body` once all the children have finished.
+    ///
+    /// Like any task of this core, the children can borrow anything that
+    /// outlives `'a`.  The scope makes sure none of them is still running
+    /// when its future resolves, so data shared with them through `Rc` can
+    /// be taken back afterwards.  See
+    /// [`ScopeFuture`](struct.ScopeFuture.html) for what happens on errors.
+    pub fn scope<B, R>(&self, body: B) -> ScopeFuture<'a, R::Future>
+        where B: FnOnce(&Scope<'a>) -> R, R: IntoFuture
+    {
+        ScopeFuture::new(self.clone(), body)
+    }
+
+    /// Spawn a future that is not polled until the task of `dependency` has
+    /// finished or was dropped.
+    ///
+    /// Until then the task stays out of the ready queue, so it costs nothing
+    /// to the executor loop.  The output of the dependency is left in the
+    /// handle.
+    pub fn spawn_after<T, E, F>(&self, dependency: &JoinHandle<T, E>, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        if let Some((ticket, _)) = self.spawn_parked(f) {
+            dependency.unpark_when_finished(ticket);
+        }
+    }
+
+    /// Spawn a future that is not polled until the task of `dependency` has
+    /// finished with an item, and return a
+    /// [`JoinHandle`](struct.JoinHandle.html) that resolves to its output.
+    ///
+    /// If the dependency fails or is dropped instead, the future is dropped
+    /// without being polled, and the returned handle fails with
+    /// `JoinError::Canceled`.  Chaining these calls expresses an ordered
+    /// startup, such as connecting, then authenticating, then subscribing.
+    pub fn spawn_after_task<T, E, F>(&self, dependency: &JoinHandle<T, E>,
+                                     f: F)
+                                     -> JoinHandle<F::Item, F::Error>
+        where T: 'a, E: 'a,
+              F: IntoFuture, F::Future: 'a, F::Item: 'a, F::Error: 'a
+    {
+        let (task, handle) = JoinHandle::new(f.into_future());
+        let task = dependency.succeeded().then(|result| match result {
+            Ok(()) => future::Either::A(task),
+            Err(()) => future::Either::B(future::ok(())),
+        });
+        if let Some((ticket, id)) = self.spawn_parked(task) {
+            handle.set_id(id);
+            dependency.unpark_when_finished(ticket);
+        }
+        handle
+    }
+
+    /// Spawn a future without queuing it, so that it is not polled until
+    /// [`TaskHandle::start`](struct.TaskHandle.html#method.start) is called.
+    ///
+    /// This stages tasks to be released later, possibly from another thread.
+    /// If the core is gone, the future is dropped and starting the returned
+    /// handle does nothing.
+    pub fn spawn_paused<F>(&self, f: F) -> TaskHandle
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        TaskHandle::new(self.spawn_parked(f))
+    }
+
+    /// Spawn a future without queuing it, and return what queues it once
+    /// unparked along with its id, unless the core is gone.
+    fn spawn_parked<F>(&self, f: F) -> Option<(Arc<Unpark>, TaskId)>
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        let inner = self.upgrade()?;
+        let mut inner = inner.borrow_mut();
+        let f = inner.small_box(f.into_future());
+        let aux = inner.insert(f, any::type_name::<F::Future>())?;
+        let spawned = inner.spawns[aux].as_ref().unwrap();
+        Some((spawned.ticket.clone(), spawned.id))
+    }
+
+    /// Spawn the tasks of `graph`.  Each task is held out of the ready queue
+    /// until the tasks it depends on have finished.
+    pub fn spawn_graph<E: 'a>(&self, graph: TaskGraph<'a, E>)
+                              -> GraphFuture<E> {
+        graph.spawn(self)
+    }
+
+    /// Spawn a future and return a [`JoinHandle`](struct.JoinHandle.html)
+    /// that resolves to its output.
+    ///
+    /// Unlike [`spawn_future`](#method.spawn_future), the future is spawned
+    /// right away, whether or not the handle is ever polled.
+    pub fn spawn_handle<F>(&self, f: F) -> JoinHandle<F::Item, F::Error>
+        where F: IntoFuture, F::Future: 'a, F::Item: 'a, F::Error: 'a
+    {
+        let (task, handle) = JoinHandle::new(f.into_future());
+        if let Some(inner) = self.upgrade() {
+            let mut inner = inner.borrow_mut();
+            let task = inner.small_box(task);
+            if let Some(id) = inner.spawn(task, any::type_name::<F::Future>()) {
+                handle.set_id(id);
+            }
+        }
+        handle
+    }
+
+    /// Spawn a future as its own task and then return a future that can be
+    /// used to query its result, or `JoinError::Canceled` if the task is
+    /// dropped first.
+    pub fn spawn_future<F: IntoFuture>(&self, future: F)
+                                       -> SpawnFuture<'a, F::Future> {
+        SpawnFuture::new(self.clone(), future)
+    }
+
+    /// Like [`spawn_future`](#method.spawn_future), but polls the future once
+    /// in place before spawning it.  If it is ready then, its output is
+    /// returned right away, without a task, which saves the turns it takes
+    /// to hand the output over.
+    ///
+    /// That first poll runs within the task polling the returned future, so
+    /// the wakers, coop budget, task id, context and current handle that the
+    /// future sees during it are those of that task.  Futures that rely on
+    /// them being their own should use `spawn_future` instead.
+    pub fn spawn_future_inline<F: IntoFuture>(&self, future: F)
+                                              -> SpawnFuture<'a, F::Future> {
+        SpawnFuture::inline(self.clone(), future)
+    }
+
+    /// Register a hook that tears something down when the core is shut down
+    /// with [`Core::shutdown`](struct.Core.html#method.shutdown), such as
+    /// flushing a buffer or closing a socket.  `factory` creates the future
+    /// that does it at that time.
+    ///
+    /// The hooks are run in the reverse order of their registration, so
+    /// that components are torn down before those they depend on.  Nothing
+    /// is run if the core is merely dropped.
+    pub fn on_shutdown<F, R>(&self, factory: F)
+        where F: FnOnce() -> R + 'a,
+              R: IntoFuture<Item=(), Error=Void> + 'a
+    {
+        if let Some(inner) = self.upgrade() {
+            inner.borrow_mut().shutdown_hooks.push(Box::new(move || {
+                Box::new(factory().into_future())
+                    as Box<Future<Item=(), Error=Void>>
+            }));
+        }
+    }
+
+    /// Whether the core of this handle still exists.
+    pub fn is_alive(&self) -> bool {
+        self.live().is_some()
+    }
+
+    /// Whether both handles refer to the same core.  Handles to a core that
+    /// was dropped, or given back to its pool, only compare equal to the
+    /// handles of that core created before.
+    pub fn same_core(&self, other: &Handle<'a>) -> bool {
+        self.0.ptr_eq(&other.0) && self.2 == other.2
+    }
+
+    /// Register a component that has to warm up before the core is ready,
+    /// and return the token it uses to tell when it is.
+    pub fn ready_token(&self) -> ReadyToken {
+        self.readiness().register()
+    }
+
+    /// A future that resolves once every
+    /// [`ReadyToken`](struct.ReadyToken.html) handed out so far, and any
+    /// handed out while waiting, has been used.  If the core no longer
+    /// exists, it resolves right away, since there is nothing left to wait
+    /// for.
+    pub fn all_ready(&self) -> AllReady {
+        match self.upgrade() {
+            Some(inner) => inner.borrow().readiness.all_ready(0),
+            None => AllReady::gone(),
+        }
+    }
+
+    fn readiness(&self) -> Readiness {
+        self.upgrade()
+            .map(|inner| inner.borrow().readiness.clone())
+            .unwrap_or_default()
+    }
+
+    /// Turn this handle into a [`StrongHandle`](struct.StrongHandle.html),
+    /// or return `None` if the core no longer exists.
+    pub fn to_strong(&self) -> Option<StrongHandle<'a>> {
+        self.upgrade().map(|inner| StrongHandle {
+            inner: inner,
+            handle: self.clone(),
+        })
+    }
+}
+
+/// A [`Handle`](struct.Handle.html) that keeps the tasks of its
+/// [`Core`](struct.Core.html) alive.
+///
+/// A plain `Handle` only refers weakly to the core, so spawning through it
+/// does nothing once the core is dropped.  A `StrongHandle` owns a reference
+/// to the tasks instead, so everything spawned through it lands.  Note that
+/// the tasks still only run while the `Core` is run.
+///
+/// Dropping the `Core` drops the tasks it has, whatever `StrongHandle`s are
+/// left, so a task may keep a `StrongHandle` to its own core.  The tasks
+/// spawned through a `StrongHandle` after that are only dropped with the
+/// last `StrongHandle`, though: one of them holding a `StrongHandle` itself
+/// forms a reference cycle, and leaks along with the rest of the core.
+///
+/// It dereferences to a `Handle`, so all the spawning methods are available.
+#[derive(Debug, Clone)]
+pub struct StrongHandle<'a> {
+    inner: Rc<RefCell<Inner<'a>>>,
+    handle: Handle<'a>,
+}
+
+impl<'a> StrongHandle<'a> {
+    /// A weak [`Handle`](struct.Handle.html) to the same core.
+    pub fn handle(&self) -> Handle<'a> {
+        Handle(Rc::downgrade(&self.inner), self.handle.1, self.handle.2)
+    }
+}
+
+impl<'a> Deref for StrongHandle<'a> {
+    type Target = Handle<'a>;
+    fn deref(&self) -> &Handle<'a> {
+        &self.handle
+    }
+}
+
+/// A [`Handle`](struct.Handle.html) to a `Core<'static>`.
+///
+/// This can be stored in application types without making them generic over
+/// the lifetime of the executor.  Since it is just an alias, a
+/// `Handle<'static>` converts to and from an `OwnedHandle` for free.
+pub type OwnedHandle = Handle<'static>;
+
+#[cfg(feature = "futures-spawn")]
+impl<'a, F> futures_spawn::Spawn<F> for Handle<'a>
+    where F: Future<Item=(), Error=()> + 'a
+{
+    fn spawn_detached(&self, f: F) {
+        self.spawn_or_drop(f.or_else(|_| Ok(())))
+    }
+}
+
+/// Number of spawned tasks of a [`Core`](struct.Core.html) in each state.
+///
+/// See [`Core::counts`](struct.Core.html#method.counts).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TaskCounts {
+    /// Number of spawned tasks that have not finished.
+    pub total: usize,
+    /// Number of tasks waiting to be polled, including those woken during
+    /// the current poll and those spawned to be polled first.
+    pub queued: usize,
+    /// Number of tasks waiting to be woken.
+    pub idle: usize,
+    /// Number of tasks being polled: one if the counts are taken from
+    /// within a spawned task, zero otherwise.
+    pub running: usize,
+}
+
+/// How busy the executor is, as reported by [`pressure`](fn.pressure.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pressure {
+    /// Fewer than 16 tasks are waiting to be polled.
+    Low,
+    /// Between 16 and 255 tasks are waiting to be polled.
+    Medium,
+    /// 256 or more tasks are waiting to be polled.
+    High,
+}
+
+/// Report how many other tasks are ready and waiting to be polled on the
+/// executor running the current task.
+///
+/// Tasks can use this to adapt to the load, e.g. by batching more work when
+/// the executor is busy.  Outside of a task, this returns `Pressure::Low`.
+pub fn pressure() -> Pressure {
+    let depth = batch::queue_depth().unwrap_or(0);
+    if depth < 16 {
+        Pressure::Low
+    } else if depth < 256 {
+        Pressure::Medium
+    } else {
+        Pressure::High
+    }
+}
+
+/// Run a future on a new core until it has completed, and so have the tasks
+/// it spawned, and then drop the core.
+///
+/// `f` is given a handle to the core to create the future, and to spawn
+/// tasks that the future may depend on.  This runs a small graph of tasks
+/// from synchronous code, such as a request handler, without keeping a core
+/// around.  As with
+/// [`Core::run_until_done`](struct.Core.html#method.run_until_done), daemon
+/// tasks are dropped rather than waited for, and if the future fails, the
+/// remaining tasks are dropped along with the core.
+pub fn scoped_run<'a, F, R>(f: F) -> Result<R::Item, R::Error>
+    where F: FnOnce(&Handle<'a>) -> R, R: IntoFuture
+{
+    let mut core = Core::default();
+    let future = f(&core.handle());
+    core.run_until_done(future)
+}
+
+/// Unpark the current task if the `status` is `Some(Ok(NotReady))` or `None`.
+fn yield_turn<T, E>(status: Option<Poll<T, E>>) -> Poll<T, E> {
+    let result = status.unwrap_or(Ok(Async::NotReady));
+    if let Ok(Async::NotReady) = result {
+        task::park().unpark();
+    }
+    result
+}
+
+/// A combined `Core` and future `F` that can be run.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct RunFuture<'b, 'a: 'b, F> {
+    core: &'b mut Core<'a>,
+    spawned: Spawned<F>,
+}
+
+impl<'b, 'a, F: Future> RunFuture<'b, 'a, F> {
+    /// Run the future `F` on the current thread until completion.  Spawned
+    /// tasks are run concurrently as well, but may or may not complete.
+    pub fn run(&mut self) -> Result<F::Item, F::Error> {
+        loop {
+            match self.turn().unwrap_or(Ok(Async::NotReady))? {
+                Async::Ready(x) => return Ok(x),
+                Async::NotReady => continue,
+            }
+        }
+    }
+
+    /// Perform one iteration of the executor loop.  Returns `None` if all
+    /// tasks are parked (no apparent progress was made).
+    pub fn turn(&mut self) -> Option<Poll<F::Item, F::Error>> {
+        self.core.turn_with(Ok(&mut self.spawned))
+    }
+
+    /// Turn the executor until the future completes or every task, the
+    /// future included, is parked.  Returns `Ok(Async::NotReady)` in the
+    /// latter case.  See
+    /// [`Core::run_until_stalled`](struct.Core.html#method.run_until_stalled).
+    pub fn run_until_stalled(&mut self) -> Poll<F::Item, F::Error> {
+        while !self.core.is_stalled() {
+            match self.turn() {
+                None | Some(Ok(Async::NotReady)) => {}
+                Some(result) => return result,
+            }
+        }
+        Ok(Async::NotReady)
+    }
+
+    /// Stop running the future as the main future and spawn it on the core
+    /// instead, which releases the borrow of the core.  The future keeps
+    /// running whenever the core is turned, like any other task.
+    pub fn detach(self) where F: Future<Item=(), Error=Void> + 'a {
+        let RunFuture { core, spawned } = self;
+        spawned.ticket.deactivate();
+        let future = spawned.spawn.into_inner();
+        let mut inner = core.0.borrow_mut();
+        let future = inner.small_box(future);
+        inner.spawn(future, any::type_name::<F>());
+    }
+
+    /// Access the underlying `Core` between turns, e.g. to read its
+    /// statistics or spawn more tasks.
+    ///
+    /// Only running the core requires exclusive access, so everything else
+    /// remains available while the `RunFuture` is alive.
+    pub fn core(&self) -> &Core<'a> {
+        self.core
+    }
+}
+
+impl<'b, 'a, F: Future> Future for RunFuture<'b, 'a, F> {
+    type Item = F::Item;
+    type Error = F::Error;
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        yield_turn(self.turn())
+    }
+}
+
+/// The task executor.
+#[derive(Debug, Default)]
+pub struct Core<'a>(Rc<RefCell<Inner<'a>>>);
+
+impl Core<'static> {
+    /// Create an [`OwnedHandle`](type.OwnedHandle.html) to this executor.
+    ///
+    /// This is the same as [`handle`](#method.handle), but spells out the
+    /// lifetime-free type.
+    pub fn owned_handle(&self) -> OwnedHandle {
+        self.handle()
+    }
+
+    /// Make [`Handle::current`](struct.Handle.html#method.current) return a
+    /// handle to this core while it polls its tasks.
+    ///
+    /// Only a `Core<'static>` can do so, since the handle returned by
+    /// `Handle::current` cannot tell the lifetime of its core.
+    #[cfg(feature = "turn-context")]
+    pub fn enable_current_handle(&self) {
+        let handle = self.handle();
+        self.0.borrow().turn_context.set_handle(handle);
+    }
+}
+
+impl<'a> Core<'a> {
+    /// Create an executor that uses the given
+    /// [`ReadyQueue`](trait.ReadyQueue.html) to schedule its tasks instead of
+    /// the default `IndexQueue`.
+    pub fn with_queue<Q: ReadyQueue + 'static>(queue: Q) -> Self {
+        Core(Rc::new(RefCell::new(Inner::with_queue(Box::new(queue)))))
+    }
+
+    /// Create a [`Handle`](struct.Handle.html) to this executor, which can be
+    /// used to [`spawn`](struct.Handle.html#method.spawn) additional tasks.
+    pub fn handle(&self) -> Handle<'a> {
+        let generation = self.0.borrow().generation;
+        Handle(Rc::downgrade(&self.0), Affinity::current(), generation)
+    }
+
+    /// Create a [`StrongHandle`](struct.StrongHandle.html) to this executor,
+    /// through which spawning still succeeds once the core is dropped.
+    pub fn strong_handle(&self) -> StrongHandle<'a> {
+        StrongHandle {
+            inner: self.0.clone(),
+            handle: self.handle(),
+        }
+    }
+
+    /// Spawn a new task into the executor, like
+    /// [`Handle::spawn`](struct.Handle.html#method.spawn) but without going
+    /// through a handle.  The future is given back if the core has as many
+    /// tasks as [`set_max_tasks`](#method.set_max_tasks) allows.
+    pub fn spawn<F>(&self, f: F) -> Result<(), SpawnError<F>>
+        where F: IntoFuture<Item=()>, F::Future: 'a, F::Error: fmt::Debug + 'a
+    {
+        let mut inner = self.0.borrow_mut();
+        if inner.is_full() {
+            return Err(SpawnError::Full(f));
+        }
+        if inner.ids_exhausted() {
+            return Err(SpawnError::IdsExhausted(f));
+        }
+        let f = ReportError::new(f.into_future(), Rc::downgrade(&self.0));
+        let f = inner.small_box(f);
+        inner.spawn(f, any::type_name::<F::Future>());
+        Ok(())
+    }
+
+    /// Spawn a new task and poll it once right away, rather than when its
+    /// turn in the ready queue comes.
+    pub fn spawn_and_poll_now<F>(&mut self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        let id = {
+            let mut inner = self.0.borrow_mut();
+            let f = inner.small_box(f.into_future());
+            inner.insert_parked(f, any::type_name::<F::Future>())
+        };
+        if let Some(id) = id {
+            self.poke_now(id);
+        }
+    }
+
+    /// Spawn a future and return a [`JoinHandle`](struct.JoinHandle.html)
+    /// that resolves to its output, like
+    /// [`Handle::spawn_handle`](struct.Handle.html#method.spawn_handle).
+    pub fn spawn_handle<F>(&self, f: F) -> JoinHandle<F::Item, F::Error>
+        where F: IntoFuture, F::Future: 'a, F::Item: 'a, F::Error: 'a
+    {
+        let (task, handle) = JoinHandle::new(f.into_future());
+        let mut inner = self.0.borrow_mut();
+        let task = inner.small_box(task);
+        if let Some(id) = inner.spawn(task, any::type_name::<F::Future>()) {
+            handle.set_id(id);
+        }
+        handle
+    }
+
+    /// Run the given future on the current thread until completion.  Spawned
+    /// tasks are run concurrently as well, but may or may not complete: use
+    /// [`run_until_done`](#method.run_until_done) to wait for them.
+    ///
+    /// This is equivalent to `self.run_future().run()`.
+    pub fn run<F: IntoFuture>(&mut self, f: F) -> Result<F::Item, F::Error> {
+        self.run_future(f).run()
+    }
+
+    /// Run the spawned tasks on the current thread until all of them have
+    /// completed, daemon tasks included, without a main future.
+    pub fn run_all(&mut self) {
+        loop {
+            if let Some(Ok(Async::Ready(()))) = self.turn::<Void>() {
+                return;
+            }
+        }
+    }
+
+    /// Turn the executor until every task is parked, or there is none left,
+    /// and then return, so that the tasks can be woken by other means in
+    /// between.  This suits tests that check the state reached by the tasks
+    /// after each event, and event loops that drive the core themselves.
+    ///
+    /// Unlike [`turn`](#method.turn), this never waits as the idle strategy
+    /// says, since it returns instead.
+    pub fn run_until_stalled(&mut self) {
+        while !self.is_stalled() {
+            self.turn::<Void>();
+        }
+    }
+
+    /// Spawn the injected futures, and then tell whether no task is ready
+    /// to be polled.
+    fn is_stalled(&self) -> bool {
+        self.poll_injected();
+        let inner = self.0.borrow();
+        inner.poll_first.is_empty() && inner.queue.lock().unwrap().is_empty()
+    }
+
+    /// Run the given future on the current thread until it has completed,
+    /// and so have all the spawned tasks except for the daemon ones (see
+    /// [`Handle::spawn_daemon`](struct.Handle.html#method.spawn_daemon)),
+    /// which are then dropped.
+    ///
+    /// If the future fails, its error is returned right away, and the
+    /// spawned tasks are left as they are.  They are also left as they are
+    /// if the core stalls first, i.e. no task is ready to be polled while
+    /// some that are not daemons are still parked, rather than waiting
+    /// forever for them to be woken: check
+    /// [`tasks`](#method.tasks) to tell.
+    pub fn run_until_done<F>(&mut self, f: F) -> Result<F::Item, F::Error>
+        where F: IntoFuture
+    {
+        let item = self.run(f)?;
+        while self.0.borrow().foreground > 0 {
+            if self.is_stalled() {
+                return Ok(item);
+            }
+            self.turn::<Void>();
+        }
+        self.drop_tasks();
+        Ok(item)
+    }
+
+    /// Stop the core, running its shutdown hooks first if `mode` asks for
+    /// it.  See [`Handle::on_shutdown`](struct.Handle.html#method.on_shutdown).
+    ///
+    /// Returns whether every hook ran to completion: this is false if some
+    /// were dropped because the deadline passed, or because of
+    /// `ShutdownMode::Abort`.
+    pub fn shutdown(mut self, mode: ShutdownMode) -> bool {
+        self.stop(mode)
+    }
+
+    /// Run the given future until it completes or `deadline` passes,
+    /// whichever comes first.  If the deadline passes, the future is
+    /// dropped and the core is shut down as with
+    /// [`shutdown`](#method.shutdown): the tasks are dropped, but the core
+    /// itself can still be used afterwards.
+    pub fn run_with_deadline<F>(&mut self, f: F, deadline: Instant,
+                                mode: ShutdownMode)
+                                -> RunOutcome<F::Item, F::Error>
+        where F: IntoFuture
+    {
+        {
+            let mut run = self.run_future(f);
+            while Instant::now() < deadline {
+                match run.turn() {
+                    Some(Ok(Async::Ready(item))) => {
+                        return RunOutcome::Finished(Ok(item));
+                    }
+                    Some(Err(err)) => return RunOutcome::Finished(Err(err)),
+                    _ => {}
+                }
+            }
+        }
+        RunOutcome::Stopped { hooks_completed: self.stop(mode) }
+    }
+
+    /// Start the tasks staged with
+    /// [`Handle::spawn_paused`](struct.Handle.html#method.spawn_paused) at
+    /// `at`, or as close to it as possible, and report how close it got.
+    ///
+    /// Until then, the core keeps polling the tasks that are ready and spins
+    /// when there are none, so that it is not parked at the target time.
+    /// A task that takes long to poll right before the target time delays
+    /// the release, which shows in the report.
+    pub fn release_at<I>(&mut self, at: Instant, tasks: I) -> ReleaseReport
+        where I: IntoIterator<Item=TaskHandle>
+    {
+        let tasks: Vec<_> = tasks.into_iter().collect();
+        while Instant::now() < at {
+            if !self.is_stalled() {
+                self.turn::<Void>();
+            }
+        }
+        let start = Instant::now();
+        let released = tasks.len();
+        for task in tasks {
+            task.start();
+        }
+        ReleaseReport {
+            released: released,
+            late: start - at,
+            spread: start.elapsed(),
+        }
+    }
+
+    /// Run the shutdown hooks as `mode` asks, and then drop the tasks.
+    /// Returns whether every hook ran to completion.
+    fn stop(&mut self, mode: ShutdownMode) -> bool {
+        let hooks = mem::take(&mut self.0.borrow_mut().shutdown_hooks);
+        let completed = match mode {
+            ShutdownMode::Abort => hooks.is_empty(),
+            ShutdownMode::Drain(timeout) => {
+                self.run_hooks(hooks, Instant::now() + timeout)
+            }
+        };
+        self.drop_tasks();
+        completed
+    }
+
+    fn run_hooks(&mut self, hooks: Vec<ShutdownHook<'a>>, deadline: Instant)
+                 -> bool {
+        for hook in hooks.into_iter().rev() {
+            let mut run = self.run_future(hook());
+            loop {
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                if let Some(poll) = run.turn() {
+                    if poll.void_unwrap().is_ready() {
+                        break;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Drop the tasks that have not finished, as dropping the core does.
+    fn drop_tasks(&mut self) {
+        let remaining = self.0.borrow().remaining();
+        for (id, _) in remaining {
+            let dropped = {
+                let mut inner = self.0.borrow_mut();
+                // dropping the previous task may have freed its slot or
+                // spawned into it, so the slot is looked up by id
+                let aux = match inner.find(id) {
+                    Some(aux) => aux,
+                    None => continue,
+                };
+                if let Some(ref mut abandoned) = inner.abandoned {
+                    abandoned(id);
+                }
+                let spawned = inner.remove(aux);
+                if let Some(ref spawned) = spawned {
+                    spawned.ticket.deactivate();
+                    inner.completions.publish(spawned.id,
+                                              &spawned.name,
+                                              TaskOutcome::Dropped,
+                                              spawned.spawned_at.elapsed());
+                }
+                spawned
+            };
+            // outside of the borrow, since the tasks may use their handles
+            drop(dropped);
+        }
+        self.wake_capacity_waiters();
+    }
+
+    /// Wake the tasks waiting for the number of tasks to drop below the
+    /// limit, once it may have.
+    fn wake_capacity_waiters(&self) {
+        let waiters = mem::take(&mut self.0.borrow_mut().capacity_waiters);
+        for waiter in waiters {
+            waiter.unpark();
+        }
+    }
+
+    /// Drop all the tasks and clear the state of the core, so that it can be
+    /// used again as if it were new, such as once per iteration of a
+    /// benchmark, without allocating its storage again.
+    ///
+    /// The tasks are dropped as when the core is dropped.  Then the ready
+    /// queue, the shutdown hooks, the collected results and the readiness
+    /// tokens are cleared, and so are the statistics: turns, completed
+    /// tasks, uptime, and the idle, pool and lock statistics.  The task
+    /// storage and the allocations of the pool are kept, as are the hooks,
+    /// the settings and the context values of the core.  Task ids keep
+    /// increasing, so that an id is never reused.  Futures sent through an
+    /// [`Injector`](struct.Injector.html) are not dropped.
+    pub fn reset(&mut self) {
+        self.drop_tasks();
+        let cleared = {
+            let mut inner = self.0.borrow_mut();
+            let inner = &mut *inner;
+            {
+                let mut queue = inner.queue.lock().unwrap();
+                while queue.pop_front().is_some() {}
+            }
+            inner.poll_first.clear();
+            inner.readiness = Readiness::default();
+            inner.schedule.pacing.reset();
+            if let Some(ref mut idle) = inner.schedule.idle {
+                idle.reset();
+            }
+            #[cfg(feature = "box-pool")]
+            inner.pool.reset_stats();
+            #[cfg(feature = "lock-stats")]
+            inner.queue.reset_stats();
+            inner.created = Instant::now();
+            #[cfg(feature = "turn-count")]
+            inner.turns.set(0);
+            inner.completed = 0;
+            let hooks = mem::take(&mut inner.shutdown_hooks);
+            let results: Vec<_> = inner.results.drain().collect();
+            (hooks, results)
+        };
+        // outside of the borrow, since they may use handles to the core
+        drop(cleared);
+    }
+
+    /// Make the handles created so far stop working, for a core given back to
+    /// a `CorePool`.  The current handle, if enabled, is moved over.
+    pub(crate) fn retire_handles(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.generation += 1;
+        #[cfg(feature = "turn-context")]
+        inner.turn_context.renew_handle(inner.generation);
+    }
+
+    /// Run two futures on the current thread until both have completed, or
+    /// until either one fails.
+    ///
+    /// Unlike `self.run(f.join(g))`, `g` runs as a separate task, so each
+    /// future is only polled when it has been woken itself.
+    pub fn run_two<F, G>(&mut self, f: F, g: G)
+                         -> Result<(F::Item, G::Item), F::Error>
+        where F: IntoFuture, G: IntoFuture<Error=F::Error>, G::Future: 'a
+    {
+        let g = self.handle().spawn_future(g).map_err(|err| match err {
+            JoinError::Failed(err) => err,
+            // the core is busy running `f`, so nothing can drop the task
+            JoinError::Canceled => unreachable!("task of g was dropped"),
+        });
+        self.run(f.into_future().join(g))
+    }
+
+    /// Like [`run`](#method.run), but for futures that cannot fail, so the
+    /// item is returned directly.
+    pub fn run_ok<F>(&mut self, f: F) -> F::Item
+        where F: IntoFuture<Error=Void>
+    {
+        self.run(f).void_unwrap()
+    }
+
+    /// Like [`run_ok`](#method.run_ok), but for futures whose error type is
+    /// the standard `Infallible` rather than `Void`.
+    pub fn run_infallible<F>(&mut self, f: F) -> F::Item
+        where F: IntoFuture<Error=Infallible>
+    {
+        match self.run(f) {
+            Ok(item) => item,
+            Err(err) => match err {},
+        }
+    }
+
+    /// Like [`run_ok`](#method.run_ok), but for futures that only complete
+    /// and produce no item.
+    pub fn run_unit<F>(&mut self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>
+    {
+        self.run_ok(f)
+    }
+
+    /// Run the core until `n` [`ReadyToken`](struct.ReadyToken.html)s have
+    /// been handed out and all of them have been used, e.g. to warm up all
+    /// the components before accepting traffic.
+    ///
+    /// Waiting for a number of tokens covers the components that only ask
+    /// for a token once they are first polled.
+    pub fn run_until_ready(&mut self, n: usize) {
+        let ready = self.0.borrow().readiness.all_ready(n);
+        self.run_unit(ready)
+    }
+
+    /// Like [`run`](#method.run), but creates a
+    /// [`RunFuture`](struct.RunFuture.html) object, which allows one to
+    /// manually [`turn`](struct.RunFuture.html#method.turn) the executor.
+    pub fn run_future<'b, F: IntoFuture>(&'b mut self, f: F)
+                                         -> RunFuture<'b, 'a, F::Future> {
+        let ticket = {
             let inner = self.0.borrow();
-            let popped = inner.queue.lock().unwrap().pop_front();
-            match popped {
-                None => return if inner.spawns.is_empty() {
+            // if the main spawn is still queued somehow (because the user did
+            // not complete a previous RunFuture), remove it
+            let id = SpawnId::main();
+            inner.queue.lock().unwrap().remove(id.to_queue_index());
+            inner.new_ticket(id, TaskId::main())
+        };
+        #[cfg(feature = "turn-context")]
+        {
+            self.0.borrow_mut().main = Arc::downgrade(&ticket);
+        }
+        RunFuture {
+            core: self,
+            spawned: Spawned {
+                spawn: executor::spawn(f.into_future()),
+                ticket: TaskTicket(ticket),
+                id: TaskId::main(),
+                order: 0,
+                name: None,
+                spawned_at: Instant::now(),
+                polls: 0,
+                size: mem::size_of::<F::Future>(),
+                daemon: false,
+            },
+        }
+    }
+
+    /// Register an interceptor that observes every unpark of the tasks of
+    /// this core and every poll that results from one, replacing the
+    /// previous interceptor if any.  See
+    /// [`WakeInterceptor`](trait.WakeInterceptor.html).
+    ///
+    /// To use several interceptors at once, register them as a pair, e.g.
+    #[cfg_attr(feature = "latency",
+               doc = " `(WakeLatency::default(), WakeSources::new())`.")]
+    #[cfg_attr(not(feature = "latency"),
+               doc = " `(WakeSources::new(), MyInterceptor)`.")]
+    #[cfg(feature = "intercept")]
+    pub fn set_wake_interceptor<I>(&self, interceptor: I)
+        where I: WakeInterceptor + 'static
+    {
+        self.0.borrow().interceptor.set(Arc::new(interceptor));
+    }
+
+    /// Start recording the delay between each unpark of a task and its
+    /// subsequent poll.  This registers a
+    /// [`WakeLatency`](struct.WakeLatency.html) as the
+    /// [wake interceptor](#method.set_wake_interceptor).
+    #[cfg(feature = "latency")]
+    pub fn record_wake_latency(&self) -> WakeLatency {
+        let latency = WakeLatency::default();
+        self.set_wake_interceptor(latency.clone());
+        latency
+    }
+
+    /// Start counting the unparks of tasks that come from threads other
+    /// than the current one, per thread.  This registers a
+    /// [`WakeSources`](struct.WakeSources.html) as the
+    /// [wake interceptor](#method.set_wake_interceptor).
+    #[cfg(feature = "intercept")]
+    pub fn record_wake_sources(&self) -> WakeSources {
+        let sources = WakeSources::new();
+        self.set_wake_interceptor(sources.clone());
+        sources
+    }
+
+    /// Create `n` virtual executors that run on this core, each with its own
+    /// ready queue, turn budget and statistics.  See
+    /// [`Partition`](struct.Partition.html).
+    pub fn partition(&self, n: usize) -> Vec<Partition<'a>> {
+        let handle = self.handle();
+        (0..n).map(|_| Partition::new(&handle)).collect()
+    }
+
+    /// Register an [`Injector`](struct.Injector.html), whose futures will be
+    /// spawned at the start of every turn.
+    pub fn add_injector(&self, injector: Injector) {
+        self.0.borrow_mut().injectors.push(injector);
+    }
+
+    /// Spawn all the futures currently queued in the registered injectors.
+    /// Returns the number of futures spawned.
+    ///
+    /// This is done automatically at the start of every turn.
+    pub fn poll_injected(&self) -> usize {
+        let type_name =
+            any::type_name::<Box<Future<Item=(), Error=Void> + Send>>();
+        let mut inner = self.0.borrow_mut();
+        let mut count = 0;
+        for i in 0..inner.injectors.len() {
+            let injected = inner.injectors[i].take_all();
+            count += injected.len();
+            for f in injected {
+                inner.spawn(SmallBox::from_box(f), type_name);
+            }
+        }
+        count
+    }
+
+    /// Start a watchdog thread that calls `callback` every `interval` with a
+    /// [`WatchdogReport`](struct.WatchdogReport.html), so that a wedged
+    /// executor can be detected from outside.  The callback runs on the
+    /// watchdog thread, even while the executor is stuck in a poll.
+    ///
+    /// This replaces any previous watchdog.  The thread exits once the `Core`
+    /// is dropped or the watchdog is replaced.
+    #[cfg(feature = "watchdog")]
+    pub fn set_watchdog<F>(&self, interval: Duration, callback: F)
+        where F: FnMut(&WatchdogReport) + Send + 'static
+    {
+        let watchdog = Watchdog::spawn(interval, callback);
+        self.0.borrow_mut().schedule.watchdog = Some(watchdog);
+    }
+
+    /// Start a background thread that samples the queue depth, turn rate and
+    /// task count every `interval`, keeping the last `capacity` samples.
+    ///
+    /// The core only publishes a few relaxed atomic counters once per turn,
+    /// so sampling does not perturb the executor loop.  This replaces any
+    /// previous sampler of this core.
+    #[cfg(feature = "sampler")]
+    pub fn start_sampler(&self, interval: Duration, capacity: usize)
+                         -> Sampler {
+        let counters = Arc::new(Counters::default());
+        let sampler = Sampler::spawn(Arc::downgrade(&counters),
+                                     interval,
+                                     capacity);
+        self.0.borrow_mut().counters = Some(counters);
+        sampler
+    }
+
+    /// Sizes in bytes of the futures of the spawned tasks that have not yet
+    /// completed.
+    ///
+    /// Spawned futures are moved into a box, so very large futures are
+    /// expensive to spawn even if they do little work.
+    pub fn future_sizes(&self) -> Vec<usize> {
+        self.0.borrow().spawns.iter()
+            .filter_map(|(_, spawned)| spawned.as_ref())
+            .map(|spawned| spawned.size)
+            .collect()
+    }
+
+    /// Call `warn` with the size of every future larger than `threshold`
+    /// bytes that is spawned from now on.
+    ///
+    /// The callback is invoked while the task is being spawned, so it must
+    /// not use the `Core` or its handles.  It is meant for logging.
+    pub fn set_large_future_threshold<F>(&self, threshold: usize, warn: F)
+        where F: FnMut(usize) + 'a
+    {
+        self.0.borrow_mut().large_future = Some((threshold, Box::new(warn)));
+    }
+
+    /// Limit the number of spawned tasks that have not finished to `max`,
+    /// or lift the limit with `None`, the default.
+    ///
+    /// Only [`Handle::spawn`](struct.Handle.html#method.spawn),
+    /// [`Handle::spawn_all`](struct.Handle.html#method.spawn_all) and
+    /// [`Core::spawn`](#method.spawn) enforce the limit, by handing the
+    /// futures back, so that a server can shed load instead of growing the
+    /// task arena without bounds.  The other ways to spawn have no way to
+    /// refuse a task and ignore it.
+    pub fn set_max_tasks(&self, max: Option<usize>) {
+        self.0.borrow_mut().max_tasks = max;
+        self.wake_capacity_waiters();
+    }
+
+    /// Start turns at most once every `interval`, sleeping before a turn if
+    /// needed.  This caps the CPU usage of a busy core at the cost of adding
+    /// up to `interval` of latency.  A zero interval, the default, disables
+    /// the limit.
+    pub fn set_min_turn_interval(&self, interval: Duration) {
+        self.0.borrow_mut().schedule.pacing.set_min_interval(interval);
+    }
+
+    /// Sleep for `sleep` after every turn that finds all tasks parked,
+    /// instead of trying again right away.  This caps the CPU usage of an
+    /// idle core at the cost of adding up to `sleep` of latency to wake-ups.
+    /// A zero duration, the default, disables sleeping.
+    pub fn set_idle_sleep(&self, sleep: Duration) {
+        self.0.borrow_mut().schedule.pacing.set_idle_sleep(sleep);
+    }
+
+    /// Wait according to `strategy` when all tasks are parked, instead of
+    /// turning again right away.  See
+    /// [`IdleStrategy`](struct.IdleStrategy.html).
+    ///
+    /// The strategy may park the thread, so the core must be run on the
+    /// thread that calls this.
+    pub fn set_idle_strategy(&self, strategy: IdleStrategy) {
+        let mut inner = self.0.borrow_mut();
+        if let Some(ref mut idle) = inner.schedule.idle {
+            idle.set_strategy(strategy);
+            return;
+        }
+        inner.schedule.idle = Some(Idle::new(strategy, &inner.queue));
+    }
+
+    /// What the [idle strategy](#method.set_idle_strategy) has done so far.
+    pub fn idle_stats(&self) -> IdleStats {
+        let inner = self.0.borrow();
+        inner.schedule.idle.as_ref().map(Idle::stats).unwrap_or_default()
+    }
+
+    /// Start counting how often the lock of the ready queue is acquired and
+    /// how long acquiring it takes when another thread holds it, which
+    /// happens when tasks are woken from other threads.  See
+    /// [`lock_stats`](#method.lock_stats).
+    #[cfg(feature = "lock-stats")]
+    pub fn enable_lock_stats(&self) {
+        self.0.borrow().queue.enable_stats();
+    }
+
+    /// Contention statistics of the lock of the ready queue, all zero unless
+    /// [enabled](#method.enable_lock_stats).
+    #[cfg(feature = "lock-stats")]
+    pub fn lock_stats(&self) -> LockStats {
+        self.0.borrow().queue.stats()
+    }
+
+    /// Call `hook` with a [`SpawnRecord`](struct.SpawnRecord.html) for every
+    /// task spawned from now on, e.g. to keep an audit log of the work the
+    /// core was given.
+    ///
+    /// The hook is invoked while the task is being spawned, so it must not
+    /// use the `Core` or its handles.
+    pub fn on_spawn<F>(&self, hook: F) where F: FnMut(&SpawnRecord) + 'a {
+        self.0.borrow_mut().spawn_hook = Some(Box::new(hook));
+    }
+
+    /// Call `handler` with the id and the error of every spawned task that
+    /// fails from now on, e.g. to log it.  Without a handler, the errors are
+    /// ignored.
+    ///
+    /// The handler is invoked while the task is being polled, so it must not
+    /// use the `Core` or its handles.
+    pub fn on_task_error<F>(&self, handler: F)
+        where F: FnMut(TaskId, &fmt::Debug) + 'a
+    {
+        self.0.borrow_mut().error_hook = Some(Box::new(handler));
+    }
+
+    /// Call `hook` with the id of every spawned task that has not finished
+    /// when the core is dropped, right before the task is dropped.
+    ///
+    /// The remaining tasks are dropped in the reverse order of spawning.
+    /// Their handles to the core no longer work at that point.
+    pub fn on_abandoned<F>(&self, hook: F) where F: FnMut(TaskId) + 'a {
+        self.0.borrow_mut().abandoned = Some(Box::new(hook));
+    }
+
+    /// Create a stream of [`TaskCompletion`](struct.TaskCompletion.html)s
+    /// for the spawned tasks that finish from now on.
+    ///
+    /// This allows a monitoring task on the same core to observe every other
+    /// task without wrapping them individually.
+    pub fn completions(&self) -> Completions {
+        self.0.borrow_mut().completions.subscribe()
+    }
+
+    /// Make `value` available to every task of this core through
+    /// [`synchrotron::context`](fn.context.html), keyed by its type.  This
+    /// suits state that all the tasks share, such as a clock or the
+    /// configuration, without cloning an `Rc` into each of them.  A value
+    /// set earlier with the same type is replaced.
+    #[cfg(feature = "turn-context")]
+    pub fn set_context<T: 'static>(&self, value: T) {
+        self.0.borrow().turn_context
+            .set_context(TypeId::of::<T>(), Rc::new(value));
+    }
+
+    /// Take the items of type `T` collected so far from futures spawned with
+    /// [`spawn_collect`](struct.Handle.html#method.spawn_collect), in the
+    /// order they completed.
+    pub fn take_results<T: 'static>(&self) -> Vec<T> {
+        match self.0.borrow_mut().results.remove(&TypeId::of::<T>()) {
+            None => Vec::new(),
+            Some(results) => *results.downcast::<Vec<T>>()
+                .expect("results of the wrong type"),
+        }
+    }
+
+    /// Number of iterations of the executor loop performed so far, including
+    /// those where no progress was made.
+    #[cfg(feature = "turn-count")]
+    pub fn turns(&self) -> u64 {
+        self.0.borrow().turns.get()
+    }
+
+    /// Time elapsed since this executor was created, or last
+    /// [reset](#method.reset).
+    pub fn uptime(&self) -> Duration {
+        self.0.borrow().created.elapsed()
+    }
+
+    /// Number of spawned tasks in each state.
+    ///
+    /// This only reads counters kept up to date as tasks are spawned, woken
+    /// and polled, so it is cheap enough to call on every turn.  The future
+    /// passed to [`run`](#method.run) is not counted.
+    pub fn counts(&self) -> TaskCounts {
+        let inner = self.0.borrow();
+        let total = inner.len();
+        let running = match inner.current {
+            Some(id) if id != TaskId::main() => 1,
+            _ => 0,
+        };
+        // the running task is also queued if it woke itself
+        let queued = cmp::min(inner.queued.load(Ordering::SeqCst),
+                              total - running);
+        TaskCounts {
+            total: total,
+            queued: queued,
+            idle: total - running - queued,
+            running: running,
+        }
+    }
+
+    /// List the spawned tasks that have not finished, in no particular
+    /// order.
+    ///
+    /// If called from within a task, that task is not listed.
+    pub fn tasks(&self) -> vec::IntoIter<TaskRef> {
+        let inner = self.0.borrow();
+        let tasks: Vec<_> = inner.spawns.iter()
+            .filter_map(|(_, spawned)| spawned.as_ref().map(|spawned| {
+                let state = if spawned.ticket.is_queued() {
+                    TaskState::Queued
+                } else {
+                    TaskState::Idle
+                };
+                // the core itself holds one reference to the ticket
+                TaskRef::new(spawned.id,
+                             spawned.name.clone(),
+                             state,
+                             spawned.spawned_at.elapsed(),
+                             spawned.polls,
+                             spawned.size,
+                             Arc::strong_count(&spawned.ticket) - 1)
+            }))
+            .collect();
+        tasks.into_iter()
+    }
+
+    /// Call `f` with the [`TurnContext`](struct.TurnContext.html) of the task
+    /// being polled by a core on this thread.  Returns `None` outside of a
+    /// poll.
+    ///
+    /// This is meant for code that is called from within a poll but cannot
+    /// be given a [`Handle`](struct.Handle.html), such as a callback from a C
+    /// library.
+    #[cfg(feature = "turn-context")]
+    pub fn with_turn_context<R, F>(f: F) -> Option<R>
+        where F: FnOnce(&TurnContext) -> R
+    {
+        turn_context::with(f)
+    }
+
+    /// How often the allocations of spawned futures could be reused.
+    ///
+    /// Futures too large to be stored inline are allocated from a pool of
+    /// size classes, and their allocations are kept for later futures of a
+    /// similar size once they complete.
+    #[cfg(feature = "box-pool")]
+    pub fn pool_stats(&self) -> PoolStats {
+        self.0.borrow().pool.stats()
+    }
+
+    /// Number of spawned tasks that have run to completion so far.
+    pub fn completed_tasks(&self) -> u64 {
+        self.0.borrow().completed
+    }
+
+    /// Wake the task with the given id, as if it had been unparked, so that
+    /// it is polled on a later turn.  Returns `false` if there is no such
+    /// unfinished task.
+    ///
+    /// This lets readiness signals that do not go through the task's own
+    /// `Task` handle be delivered to it.  A task cannot poke itself.
+    pub fn poke(&self, id: TaskId) -> bool {
+        let ticket = {
+            let inner = self.0.borrow();
+            inner.find(id).map(|aux| {
+                inner.spawns[aux].as_ref().unwrap().ticket.clone()
+            })
+        };
+        match ticket {
+            Some(ticket) => {
+                ticket.unpark();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Poll the task with the given id right away, ahead of the tasks already
+    /// waiting in the ready queue, whether or not it was woken.  Returns
+    /// `false` if there is no such unfinished task.
+    pub fn poke_now(&mut self, id: TaskId) -> bool {
+        let aux = {
+            let inner = self.0.borrow();
+            let aux = match inner.find(id) {
+                Some(aux) => aux,
+                None => return false,
+            };
+            // it is polled now, so a pending wake-up is used up
+            let index = SpawnId::aux(aux).to_queue_index();
+            batch::cancel(&inner.queue, index);
+            inner.queue.lock().unwrap().remove(index);
+            aux
+        };
+        self.poll_aux(aux);
+        true
+    }
+
+    /// Perform one iteration of the executor loop.  Returns `None` if all
+    /// tasks are parked (no apparent progress was made).  Returns
+    /// `Some(Ok(Ready(())))` if all spawned tasks have completed.
+    pub fn turn<T>(&mut self) -> Option<Poll<(), T>> {
+        self.turn_with::<future::Empty<(), T>>(Err(()))
+    }
+
+    /// Perform one iteration of the executor loop, optionally with a given
+    /// main spawn.  Returns `None` if all tasks are parked (no apparent
+    /// progress could be made).  If `main` is set to `Err(e)`, returns
+    /// `Some(Ok(Ready(e)))` if there are no more spawns.
+    fn turn_with<F: Future>(&mut self, main: Result<&mut Spawned<F>, F::Item>)
+                            -> Option<Poll<F::Item, F::Error>> {
+        self.0.borrow().owner.check("Core");
+        self.0.borrow_mut().schedule.before_turn();
+        self.poll_injected();
+        self.poll_first();
+        let result = self.turn_once(main);
+        let mut inner = self.0.borrow_mut();
+        let inner = &mut *inner;
+        inner.schedule.after_turn(result.is_some(), &inner.queue);
+        result
+    }
+
+    /// Poll the tasks spawned or poked to be polled before the ready queue.
+    fn poll_first(&mut self) {
+        let poll_first = mem::take(&mut self.0.borrow_mut().poll_first);
+        for id in poll_first {
+            self.poke_now(id);
+        }
+    }
+
+    /// Pop one spawn off the queue and poll it.  See
+    /// [`turn_with`](#method.turn_with).
+    fn turn_once<F: Future>(&mut self, main: Result<&mut Spawned<F>, F::Item>)
+                            -> Option<Poll<F::Item, F::Error>> {
+        let index = {
+            let inner = self.0.borrow();
+            #[cfg(feature = "turn-count")]
+            inner.turns.set(inner.turns.get() + 1);
+            match inner.pop_ready() {
+                None => return if inner.len() == 0 {
                     match main {
                         Err(item) => Some(Ok(Async::Ready(item))),
                         Ok(_) => None
@@ -310,34 +2511,123 @@ impl<'a> Core<'a> {
                 match main {
                     Err(_) => Some(Ok(Async::NotReady)),
                     Ok(main) => {
-                        let ticket = main.ticket.clone();
-                        let poll = main.spawn.poll_future(ticket);
+                        let (poll, deferred) = self.poll_spawned(main);
                         if let Ok(Async::Ready(_)) = poll {
                             main.ticket.deactivate();
                         }
+                        self.apply(deferred);
                         Some(poll)
                     }
                 }
             }
             Some(aux) => {
-                let spawned = self.0.borrow_mut().spawns.get_mut(aux)
-                    .and_then(|x| x.take());
-                if let Some(mut spawned) = spawned {
-                    let ticket = spawned.ticket.clone();
-                    let poll = spawned.spawn.poll_future(ticket);
-                    let mut inner = self.0.borrow_mut();
-                    if let Ok(Async::Ready(())) = poll {
-                        spawned.ticket.deactivate();
-                        inner.spawns.remove(aux);
-                    } else {
-                        inner.spawns[aux] = Some(spawned);
-                    }
+                self.poll_aux(aux);
+                Some(Ok(Async::NotReady))
+            }
+        }
+    }
+
+    /// Poll a task once as the current one, and return what it asked of the
+    /// [`TurnContext`](struct.TurnContext.html) along with the result, to be
+    /// [applied](#method.apply) once the task is back in place.
+    fn poll_spawned<F: Future>(&self, spawned: &mut Spawned<F>)
+                               -> (Poll<F::Item, F::Error>, Deferred) {
+        let id = spawned.id;
+        let queue = {
+            let mut inner = self.0.borrow_mut();
+            inner.current = Some(id);
+            inner.queue.clone()
+        };
+        let ticket = &spawned.ticket;
+        let spawn = &mut spawned.spawn;
+        #[cfg(feature = "turn-context")]
+        let polled = {
+            let context = self.0.borrow().turn_context.clone();
+            turn_context::poll_in(&context, id, || {
+                schedule::poll(spawn, ticket, &queue)
+            })
+        };
+        #[cfg(not(feature = "turn-context"))]
+        let polled = (schedule::poll(spawn, ticket, &queue), ());
+        spawned.polls += 1;
+        self.0.borrow_mut().current = None;
+        polled
+    }
+
+    /// Poll the spawn at `aux` in the arena, removing it if it completes.
+    fn poll_aux(&mut self, aux: usize) {
+        let spawned = self.0.borrow_mut().spawns.get_mut(aux)
+            .and_then(|x| x.take());
+        if let Some(mut spawned) = spawned {
+            let (poll, deferred) = self.poll_spawned(&mut spawned);
+            let mut inner = self.0.borrow_mut();
+            let failed = mem::take(&mut inner.failed);
+            let done = poll == Ok(Async::Ready(()));
+            if done {
+                spawned.ticket.deactivate();
+                inner.remove(aux);
+                inner.finished(&spawned);
+                inner.completed += 1;
+                let outcome = if failed {
+                    TaskOutcome::Failed
                 } else {
-                    self.0.borrow_mut().spawns.remove(aux);
+                    TaskOutcome::Completed
+                };
+                inner.completions.publish(spawned.id,
+                                          &spawned.name,
+                                          outcome,
+                                          spawned.spawned_at.elapsed());
+                #[cfg(feature = "intercept")]
+                if let Some(interceptor) = inner.interceptor.get() {
+                    interceptor.on_complete(spawned.id);
                 }
-                Some(Ok(Async::NotReady))
+            } else {
+                inner.spawns[aux] = Some(spawned);
+            }
+            drop(inner);
+            if done {
+                self.wake_capacity_waiters();
+            }
+            self.apply(deferred);
+        }
+        // otherwise the entry was stale: its task is gone, and the slot is
+        // either free or taken by a task being polled, so it is left alone
+    }
+
+    #[cfg(not(feature = "turn-context"))]
+    fn apply(&self, _: Deferred) {}
+
+    /// Carry out what was requested through the
+    /// [`TurnContext`](struct.TurnContext.html) during a poll.
+    #[cfg(feature = "turn-context")]
+    fn apply(&self, deferred: Deferred) {
+        if !deferred.spawns.is_empty() {
+            let type_name =
+                any::type_name::<Box<Future<Item=(), Error=Void>>>();
+            let mut inner = self.0.borrow_mut();
+            for f in deferred.spawns {
+                inner.spawn(SmallBox::from_box(f), type_name);
             }
         }
+        for id in deferred.wakes {
+            if id == TaskId::main() {
+                // poke only finds spawned tasks
+                let main = self.0.borrow().main.upgrade();
+                if let Some(ticket) = main {
+                    ticket.unpark();
+                }
+            } else {
+                self.poke(id);
+            }
+        }
+    }
+}
+
+impl<'a> Drop for Core<'a> {
+    fn drop(&mut self) {
+        // even if StrongHandles keep the rest of the core alive, so that
+        // tasks holding one do not keep themselves alive
+        self.drop_tasks();
     }
 }
 
@@ -0,0 +1,218 @@
+//! A single-threaded channel that passes messages by reference into a
+//! shared slab.
+//!
+//! Each message is moved into a slot of the slab once, and the receiver gets
+//! a [`Loan`](struct.Loan.html) pointing at it.  The slot is reused for a
+//! later message when the loan is dropped, so large messages passed between
+//! the stages of a pipeline on the same core are neither cloned nor boxed
+//! once the slab has grown to the number of messages in flight.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate futures;
+//! extern crate synchrotron;
+//!
+//! use futures::{Async, Stream};
+//! use synchrotron::loan;
+//!
+//! # fn main() {
+//! let (sender, mut receiver) = loan::new();
+//! sender.send([0u8; 4096]).unwrap();
+//! let message = match receiver.poll() {
+//!     Ok(Async::Ready(Some(message))) => message,
+//!     _ => unreachable!(),
+//! };
+//! assert_eq!(message.get().len(), 4096);
+//! # }
+//! ```
+
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use futures::{Async, Poll, Stream};
+use futures::task::{self, Task};
+use void::Void;
+
+// Every slot has a cell of its own, so that loans can be borrowed
+// independently of each other and of the channel.
+type Slot<T> = Rc<RefCell<Option<T>>>;
+
+struct Shared<T> {
+    // empty slots, kept for later messages
+    free: Vec<Slot<T>>,
+    // slots holding messages that have not been received yet
+    queue: VecDeque<Slot<T>>,
+    senders: usize,
+    receiver: bool,
+    waiter: Option<Task>,
+}
+
+/// Sending end of the channel.
+///
+/// It can be cloned to send from several places.
+pub struct Sender<T>(Rc<RefCell<Shared<T>>>);
+
+impl<T> Sender<T> {
+    /// If the receiver is still alive, then the message will be sent
+    /// successfully.  Otherwise, it returns `Err(value)`.
+    pub fn send(&self, value: T) -> Result<(), T> {
+        let waiter = {
+            let mut shared = self.0.borrow_mut();
+            if !shared.receiver {
+                return Err(value);
+            }
+            let slot = shared.free.pop().unwrap_or_default();
+            *slot.borrow_mut() = Some(value);
+            shared.queue.push_back(slot);
+            shared.waiter.take()
+        };
+        if let Some(waiter) = waiter {
+            waiter.unpark();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.borrow_mut().senders += 1;
+        Sender(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waiter = {
+            let mut shared = self.0.borrow_mut();
+            shared.senders -= 1;
+            if shared.senders > 0 {
+                return;
+            }
+            shared.waiter.take()
+        };
+        if let Some(waiter) = waiter {
+            waiter.unpark();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Sender")
+            .finish()
+    }
+}
+
+/// Receiving end of the channel: a stream of loans.
+///
+/// The stream ends once all the senders have been dropped and every message
+/// has been received.
+#[must_use = "streams do nothing unless polled"]
+pub struct Receiver<T>(Rc<RefCell<Shared<T>>>);
+
+impl<T> Stream for Receiver<T> {
+    type Item = Loan<T>;
+    type Error = Void;
+    fn poll(&mut self) -> Poll<Option<Loan<T>>, Void> {
+        let mut shared = self.0.borrow_mut();
+        match shared.queue.pop_front() {
+            Some(slot) => Ok(Async::Ready(Some(Loan {
+                shared: self.0.clone(),
+                slot: slot,
+            }))),
+            None if shared.senders == 0 => Ok(Async::Ready(None)),
+            None => {
+                shared.waiter = Some(task::park());
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // nobody can receive these anymore; they are dropped outside the
+        // borrow in case they hold senders of this channel
+        let _queue = {
+            let mut shared = self.0.borrow_mut();
+            shared.receiver = false;
+            shared.free.clear();
+            mem::take(&mut shared.queue)
+        };
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("queued", &self.0.borrow().queue.len())
+            .finish()
+    }
+}
+
+/// A received message, still stored in the slab of the channel.
+///
+/// The slot is freed when the loan is dropped.  Each slot is borrowed on its
+/// own, so the borrows returned by [`get`](#method.get) and
+/// [`get_mut`](#method.get_mut) can be held across other loans and sends on
+/// the same channel.
+pub struct Loan<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+    slot: Slot<T>,
+}
+
+impl<T> Loan<T> {
+    /// Borrow the message.
+    pub fn get<'a>(&'a self) -> Ref<'a, T> {
+        Ref::map(self.slot.borrow(),
+                 |value| value.as_ref().expect("loan of a free slot"))
+    }
+
+    /// Borrow the message mutably.
+    pub fn get_mut<'a>(&'a mut self) -> RefMut<'a, T> {
+        RefMut::map(self.slot.borrow_mut(),
+                    |value| value.as_mut().expect("loan of a free slot"))
+    }
+
+    /// Move the message out of the slab.
+    pub fn into_inner(self) -> T {
+        // dropping the loan afterwards finds the slot already free
+        let value = self.slot.borrow_mut().take();
+        value.expect("loan of a free slot")
+    }
+}
+
+impl<T> Drop for Loan<T> {
+    fn drop(&mut self) {
+        // the message is dropped before the channel is borrowed, in case it
+        // holds a sender of this channel
+        drop(self.slot.borrow_mut().take());
+        let mut shared = self.shared.borrow_mut();
+        if shared.receiver {
+            shared.free.push(self.slot.clone());
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Loan<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Loan")
+            .field(&*self.get())
+            .finish()
+    }
+}
+
+/// Create a single-threaded channel passing messages as loans.
+pub fn new<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Rc::new(RefCell::new(Shared {
+        free: Vec::new(),
+        queue: VecDeque::new(),
+        senders: 1,
+        receiver: true,
+        waiter: None,
+    }));
+    (Sender(shared.clone()), Receiver(shared))
+}
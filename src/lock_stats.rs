@@ -0,0 +1,88 @@
+use std::sync::{LockResult, Mutex, MutexGuard, TryLockError};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use super::ReadyQueue;
+
+/// Contention statistics of the lock that guards the ready queue of a
+/// [`Core`](struct.Core.html).
+///
+/// See [`Core::enable_lock_stats`](struct.Core.html#method.enable_lock_stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LockStats {
+    /// Number of times the lock was acquired.
+    pub acquisitions: u64,
+    /// Number of acquisitions that had to wait for another thread.
+    pub contended: u64,
+    /// Longest wait for the lock.
+    pub max_wait: Duration,
+}
+
+/// The ready queue of a core, shared with the wakers of its tasks.
+#[derive(Debug)]
+pub struct QueueLock {
+    queue: Mutex<Box<ReadyQueue>>,
+    enabled: AtomicBool,
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    max_wait_nanos: AtomicU64,
+}
+
+impl QueueLock {
+    pub fn new(queue: Box<ReadyQueue>) -> Self {
+        QueueLock {
+            queue: Mutex::new(queue),
+            enabled: AtomicBool::new(false),
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            max_wait_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn lock<'a>(&'a self) -> LockResult<MutexGuard<'a, Box<ReadyQueue>>> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return self.queue.lock();
+        }
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match self.queue.try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(err)) => Err(err),
+            Err(TryLockError::WouldBlock) => {
+                self.contended.fetch_add(1, Ordering::Relaxed);
+                let start = Instant::now();
+                let result = self.queue.lock();
+                let wait = start.elapsed();
+                let nanos = wait.as_secs().saturating_mul(1_000_000_000)
+                    .saturating_add(wait.subsec_nanos() as u64);
+                let mut max = self.max_wait_nanos.load(Ordering::Relaxed);
+                while nanos > max {
+                    match self.max_wait_nanos.compare_exchange_weak(
+                        max, nanos, Ordering::Relaxed, Ordering::Relaxed) {
+                        Ok(_) => break,
+                        Err(current) => max = current,
+                    }
+                }
+                result
+            }
+        }
+    }
+
+    pub fn enable_stats(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset_stats(&self) {
+        self.acquisitions.store(0, Ordering::Relaxed);
+        self.contended.store(0, Ordering::Relaxed);
+        self.max_wait_nanos.store(0, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> LockStats {
+        let nanos = self.max_wait_nanos.load(Ordering::Relaxed);
+        LockStats {
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contended: self.contended.load(Ordering::Relaxed),
+            max_wait: Duration::new(nanos / 1_000_000_000,
+                                    (nanos % 1_000_000_000) as u32),
+        }
+    }
+}
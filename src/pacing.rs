@@ -0,0 +1,53 @@
+//! Limits on how often a core turns, to cap its CPU usage.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct Pacing {
+    min_interval: Duration,
+    idle_sleep: Duration,
+    last_turn: Option<Instant>,
+}
+
+impl Pacing {
+    pub fn set_min_interval(&mut self, interval: Duration) {
+        self.min_interval = interval;
+    }
+
+    pub fn set_idle_sleep(&mut self, sleep: Duration) {
+        self.idle_sleep = sleep;
+    }
+
+    /// Forget when the last turn started.
+    pub fn reset(&mut self) {
+        self.last_turn = None;
+    }
+
+    /// How long to wait before starting a turn.  Assumes the turn starts
+    /// right after that.
+    pub fn wait_before_turn(&mut self) -> Option<Duration> {
+        if self.min_interval == Duration::from_secs(0) {
+            return None;
+        }
+        let now = Instant::now();
+        let wait = self.last_turn.and_then(|last| {
+            let elapsed = now - last;
+            if elapsed < self.min_interval {
+                Some(self.min_interval - elapsed)
+            } else {
+                None
+            }
+        });
+        self.last_turn = Some(now + wait.unwrap_or_default());
+        wait
+    }
+
+    /// How long to sleep after a turn that found no task to poll.
+    pub fn wait_when_idle(&self) -> Option<Duration> {
+        if self.idle_sleep == Duration::from_secs(0) {
+            None
+        } else {
+            Some(self.idle_sleep)
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Virtual executors that run as tasks of another core.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use futures::{Async, Future, Poll, task};
+use futures::task::Task;
+use index_queue::IndexQueue;
+use void::{ResultVoidExt, Void};
+use super::{Core, Handle, ReadyQueue};
+
+/// The task of the parent core that drives a partition.
+type DriverSlot = Arc<Mutex<Option<Task>>>;
+
+/// A ready queue that wakes the driver whenever a task becomes ready.
+#[derive(Debug)]
+struct WakeQueue<Q> {
+    queue: Q,
+    driver: DriverSlot,
+}
+
+impl<Q: ReadyQueue> ReadyQueue for WakeQueue<Q> {
+    fn push_back(&mut self, index: usize) {
+        self.queue.push_back(index);
+        if let Some(ref driver) = *self.driver.lock().unwrap() {
+            driver.unpark();
+        }
+    }
+
+    fn pop_front(&mut self) -> Option<usize> {
+        self.queue.pop_front()
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.queue.remove(index);
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.queue.contains(index)
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/// Number of turns a partition performs per poll unless configured
+/// otherwise.
+const DEFAULT_BUDGET: usize = 32;
+
+/// Turns the partition's core at most `budget` times per poll.
+struct Driver<'a> {
+    core: Core<'a>,
+    budget: Rc<Cell<usize>>,
+    slot: DriverSlot,
+}
+
+impl<'a> Future for Driver<'a> {
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<(), Void> {
+        *self.slot.lock().unwrap() = Some(task::park());
+        let budget = self.budget.get();
+        if budget == 0 {
+            return Ok(Async::NotReady);
+        }
+        for _ in 0..budget {
+            let poll = match self.core.turn::<Void>() {
+                None => return Ok(Async::NotReady),
+                Some(poll) => poll.void_unwrap(),
+            };
+            if let Async::Ready(()) = poll {
+                // no tasks left: keep waiting for new spawns as long as the
+                // partition itself is alive
+                return if Rc::strong_count(&self.core.0) == 1 {
+                    Ok(Async::Ready(()))
+                } else {
+                    Ok(Async::NotReady)
+                };
+            }
+        }
+        // out of budget: let the other tasks of the parent core run
+        task::park().unpark();
+        Ok(Async::NotReady)
+    }
+}
+
+/// A virtual executor that shares the thread of a parent
+/// [`Core`](struct.Core.html), created by
+/// [`Core::partition`](struct.Core.html#method.partition).
+///
+/// A partition has its own ready queue and statistics, which are available
+/// through [`core`](#method.core).  It runs as a single task of the parent
+/// core that performs at most [`budget`](#method.budget) turns each time it
+/// is polled, so a busy partition cannot starve the tasks of the parent or
+/// of other partitions.
+///
+/// Tasks left in the partition when it is dropped still run to completion.
+#[derive(Debug)]
+pub struct Partition<'a> {
+    core: Core<'a>,
+    budget: Rc<Cell<usize>>,
+    slot: DriverSlot,
+}
+
+impl<'a> Partition<'a> {
+    pub(crate) fn new(parent: &Handle<'a>) -> Self {
+        let slot = DriverSlot::default();
+        let core = Core::with_queue(WakeQueue {
+            queue: IndexQueue::default(),
+            driver: slot.clone(),
+        });
+        let budget = Rc::new(Cell::new(DEFAULT_BUDGET));
+        parent.spawn_or_drop(Driver {
+            core: Core(core.0.clone()),
+            budget: budget.clone(),
+            slot: slot.clone(),
+        });
+        Partition {
+            core: core,
+            budget: budget,
+            slot: slot,
+        }
+    }
+
+    /// The executor of this partition.  Tasks are spawned onto the
+    /// partition through its [`handle`](struct.Core.html#method.handle).
+    ///
+    /// The partition is driven by its parent, so running this core
+    /// directly is not possible.
+    pub fn core(&self) -> &Core<'a> {
+        &self.core
+    }
+
+    /// Maximum number of turns performed each time the parent polls the
+    /// partition.  Defaults to 32.
+    pub fn budget(&self) -> usize {
+        self.budget.get()
+    }
+
+    /// Set the maximum number of turns performed each time the parent polls
+    /// the partition.  A budget of zero pauses the partition.
+    pub fn set_budget(&self, budget: usize) {
+        self.budget.set(budget);
+        self.wake_driver();
+    }
+
+    fn wake_driver(&self) {
+        if let Some(ref driver) = *self.slot.lock().unwrap() {
+            driver.unpark();
+        }
+    }
+}
+
+impl<'a> Drop for Partition<'a> {
+    fn drop(&mut self) {
+        // let the driver notice that it can finish once the tasks are done
+        self.wake_driver();
+    }
+}
@@ -0,0 +1,142 @@
+//! Reuse of the allocations of spawned futures that are too large to be
+//! stored inline.
+//!
+//! Allocations are grouped in power-of-two size classes.  When a pooled
+//! future is dropped, its allocation is kept on the free list of its class
+//! for the next future of a similar size, which spares the allocator when
+//! many short tasks are spawned and completed.
+
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// Alignment of all pooled allocations.
+pub const ALIGN: usize = 16;
+
+/// The smallest size class holds `1 << MIN_CLASS` bytes.
+const MIN_CLASS: u32 = 7;
+
+/// Number of size classes: the largest holds `1 << (MIN_CLASS + CLASSES - 1)`
+/// bytes.  Larger futures are boxed as usual.
+const CLASSES: usize = 6;
+
+/// Number of free allocations kept per size class.
+const MAX_FREE: usize = 256;
+
+/// How often the pool of a [`Core`](struct.Core.html) could reuse an
+/// allocation for a spawned future.
+///
+/// Only futures too large to be stored inline, but small enough for a size
+/// class of the pool, are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Allocations that were reused.
+    pub hits: u64,
+    /// Allocations that had to be made.
+    pub misses: u64,
+}
+
+impl PoolStats {
+    /// Fraction of the allocations that were reused, or `None` if none
+    /// were requested.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            None
+        } else {
+            Some(self.hits as f64 / total as f64)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BoxPool {
+    free: RefCell<[Vec<NonNull<u8>>; CLASSES]>,
+    stats: Cell<PoolStats>,
+}
+
+impl fmt::Debug for BoxPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BoxPool")
+            .field("stats", &self.stats.get())
+            .finish()
+    }
+}
+
+fn layout(class: usize) -> Layout {
+    Layout::from_size_align(1 << (MIN_CLASS as usize + class), ALIGN)
+        .expect("invalid size class")
+}
+
+impl BoxPool {
+    /// Size class of an allocation of `size` bytes, if any.
+    pub fn class(size: usize) -> Option<usize> {
+        let bits = size.next_power_of_two().trailing_zeros();
+        let class = bits.saturating_sub(MIN_CLASS) as usize;
+        if class < CLASSES {
+            Some(class)
+        } else {
+            None
+        }
+    }
+
+    /// Get an allocation of the given class.
+    pub fn acquire(&self, class: usize) -> NonNull<u8> {
+        let reused = self.free.borrow_mut()[class].pop();
+        let mut stats = self.stats.get();
+        let ptr = match reused {
+            Some(ptr) => {
+                stats.hits += 1;
+                ptr
+            }
+            None => {
+                stats.misses += 1;
+                let layout = layout(class);
+                // SAFETY: size classes are never empty
+                let ptr = unsafe { alloc::alloc(layout) };
+                match NonNull::new(ptr) {
+                    Some(ptr) => ptr,
+                    None => alloc::handle_alloc_error(layout),
+                }
+            }
+        };
+        self.stats.set(stats);
+        ptr
+    }
+
+    /// Give back an allocation made by `acquire` for the same class.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `acquire(class)` and must not be used anymore.
+    pub unsafe fn release(&self, class: usize, ptr: NonNull<u8>) {
+        let mut free = self.free.borrow_mut();
+        if free[class].len() < MAX_FREE {
+            free[class].push(ptr);
+        } else {
+            alloc::dealloc(ptr.as_ptr(), layout(class));
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.stats.get()
+    }
+
+    /// Clear the statistics, keeping the free allocations.
+    pub fn reset_stats(&self) {
+        self.stats.set(PoolStats::default());
+    }
+}
+
+impl Drop for BoxPool {
+    fn drop(&mut self) {
+        for (class, free) in self.free.borrow_mut().iter_mut().enumerate() {
+            for ptr in free.drain(..) {
+                // SAFETY: the free lists only hold allocations made by
+                // `acquire` for their class
+                unsafe { alloc::dealloc(ptr.as_ptr(), layout(class)) };
+            }
+        }
+    }
+}
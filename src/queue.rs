@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::fmt;
+use index_queue::IndexQueue;
+
+/// The queue of tasks that are ready to be polled.
+///
+/// Tasks are identified by small indices.  Implementations must keep each
+/// index at most once: pushing an index that is already queued does nothing.
+/// The queue is shared with the wakers of the tasks, so it must be `Send`.
+///
+/// A `Core` uses an `IndexQueue` unless another queue is given to
+/// [`Core::with_queue`](struct.Core.html#method.with_queue).
+pub trait ReadyQueue: fmt::Debug + Send {
+    /// Add `index` to the back of the queue, unless it is already queued.
+    fn push_back(&mut self, index: usize);
+
+    /// Remove and return the index at the front of the queue.
+    fn pop_front(&mut self) -> Option<usize>;
+
+    /// Remove `index` from the queue if it is queued.
+    fn remove(&mut self, index: usize);
+
+    /// Whether `index` is queued.
+    ///
+    /// This is only used to report task states.  The default implementation
+    /// returns `false`, so that every task is reported as idle.
+    fn contains(&self, _index: usize) -> bool {
+        false
+    }
+
+    /// Number of queued indices.
+    fn len(&self) -> usize;
+
+    /// Whether the queue is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ReadyQueue for IndexQueue {
+    fn push_back(&mut self, index: usize) {
+        IndexQueue::push_back(self, index);
+    }
+
+    fn pop_front(&mut self) -> Option<usize> {
+        IndexQueue::pop_front(self)
+    }
+
+    fn remove(&mut self, index: usize) {
+        IndexQueue::remove(self, index);
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        IndexQueue::contains(self, index)
+    }
+
+    fn len(&self) -> usize {
+        IndexQueue::len(self)
+    }
+}
+
+/// A [`ReadyQueue`](trait.ReadyQueue.html) backed by a `VecDeque`.
+///
+/// Removal is lazy: removed entries stay in the deque and are skipped when
+/// popped, until they outnumber the queued ones and the deque is compacted.
+/// This makes every operation amortized O(1) and keeps the entries
+/// contiguous in memory, at the cost of at most twice the space.
+#[derive(Debug, Default)]
+pub struct FifoQueue {
+    entries: VecDeque<(usize, u64)>,
+    // sequence number of the live entry of each index, if it is queued
+    live: Vec<Option<u64>>,
+    next_seq: u64,
+    len: usize,
+}
+
+impl FifoQueue {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReadyQueue for FifoQueue {
+    fn push_back(&mut self, index: usize) {
+        if index >= self.live.len() {
+            self.live.resize(index + 1, None);
+        }
+        if self.live[index].is_some() {
+            return;
+        }
+        self.live[index] = Some(self.next_seq);
+        self.entries.push_back((index, self.next_seq));
+        self.next_seq += 1;
+        self.len += 1;
+    }
+
+    fn pop_front(&mut self) -> Option<usize> {
+        while let Some((index, seq)) = self.entries.pop_front() {
+            if self.live[index] == Some(seq) {
+                self.live[index] = None;
+                self.len -= 1;
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    fn remove(&mut self, index: usize) {
+        if let Some(live) = self.live.get_mut(index) {
+            if live.take().is_some() {
+                self.len -= 1;
+            }
+        }
+        // each compaction follows at least `len` removals, which pay for it
+        if self.entries.len() > 2 * self.len {
+            let live = &self.live;
+            self.entries.retain(|&(index, seq)| live[index] == Some(seq));
+        }
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.live.get(index).and_then(|&live| live).is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use futures::{Async, Future, Poll};
+use futures::task::{self, Task};
+use void::Void;
+
+#[derive(Default)]
+struct State {
+    registered: usize,
+    ready: usize,
+    waiters: Vec<Task>,
+}
+
+/// The tokens handed out by a core, and the tasks waiting for all of them.
+#[derive(Clone, Default)]
+pub struct Readiness(Rc<RefCell<State>>);
+
+impl fmt::Debug for Readiness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.0.borrow();
+        f.debug_struct("Readiness")
+            .field("registered", &state.registered)
+            .field("ready", &state.ready)
+            .finish()
+    }
+}
+
+impl Readiness {
+    pub fn register(&self) -> ReadyToken {
+        self.0.borrow_mut().registered += 1;
+        ReadyToken(self.clone())
+    }
+
+    pub fn all_ready(&self, min: usize) -> AllReady {
+        AllReady {
+            readiness: Some(self.clone()),
+            min: min,
+        }
+    }
+}
+
+/// Signals that a component has warmed up.
+///
+/// Created by [`Handle::ready_token`](struct.Handle.html#method.ready_token).
+/// Dropping a token without calling [`ready`](#method.ready) leaves it
+/// registered but not ready, so a component that fails to start holds up
+/// the futures waiting for readiness.
+#[must_use = "a ready token holds up readiness until it is used"]
+pub struct ReadyToken(Readiness);
+
+impl fmt::Debug for ReadyToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ReadyToken").finish()
+    }
+}
+
+impl ReadyToken {
+    /// Mark the component as ready, waking the tasks waiting for all the
+    /// components once it is the last one.
+    pub fn ready(self) {
+        let waiters = {
+            let mut state = (self.0).0.borrow_mut();
+            state.ready += 1;
+            if state.ready < state.registered {
+                return;
+            }
+            mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            waiter.unpark();
+        }
+    }
+}
+
+/// A future that resolves once every [`ReadyToken`](struct.ReadyToken.html)
+/// of a core has been used.
+///
+/// Created by [`Handle::all_ready`](struct.Handle.html#method.all_ready).
+#[must_use = "futures do nothing unless polled"]
+pub struct AllReady {
+    // none if the core was gone, in which case it resolves right away
+    readiness: Option<Readiness>,
+    // the number of tokens to wait for, even if fewer were handed out yet
+    min: usize,
+}
+
+impl fmt::Debug for AllReady {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AllReady")
+            .field("readiness", &self.readiness)
+            .field("min", &self.min)
+            .finish()
+    }
+}
+
+impl AllReady {
+    pub fn gone() -> Self {
+        AllReady {
+            readiness: None,
+            min: 0,
+        }
+    }
+}
+
+impl Future for AllReady {
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<(), Void> {
+        let readiness = match self.readiness {
+            Some(ref readiness) => readiness,
+            None => return Ok(Async::Ready(())),
+        };
+        let mut state = readiness.0.borrow_mut();
+        if state.ready == state.registered && state.ready >= self.min {
+            return Ok(Async::Ready(()));
+        }
+        if !state.waiters.iter().any(Task::will_notify_current) {
+            state.waiters.push(task::park());
+        }
+        Ok(Async::NotReady)
+    }
+}
@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+/// How closely [`Core::release_at`](struct.Core.html#method.release_at)
+/// released a set of staged tasks to its target time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReleaseReport {
+    /// Number of tasks released.
+    pub released: usize,
+    /// How long after the target time the first task was released.
+    pub late: Duration,
+    /// How long it took to release all the tasks after the first one.
+    pub spread: Duration,
+}
+
+impl ReleaseReport {
+    /// How long after the target time the last task was released.
+    pub fn jitter(&self) -> Duration {
+        self.late + self.spread
+    }
+}
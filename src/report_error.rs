@@ -0,0 +1,41 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc;
+use futures::{Async, Future, Poll};
+use void::Void;
+use super::Inner;
+
+/// A spawned future whose error is passed to the error hook of its core,
+/// so that the task itself cannot fail.
+pub(crate) struct ReportError<'a, F> {
+    future: F,
+    inner: rc::Weak<RefCell<Inner<'a>>>,
+}
+
+impl<'a, F> ReportError<'a, F> {
+    pub(crate) fn new(future: F, inner: rc::Weak<RefCell<Inner<'a>>>)
+                      -> Self {
+        ReportError {
+            future: future,
+            inner: inner,
+        }
+    }
+}
+
+impl<'a, F> Future for ReportError<'a, F>
+    where F: Future<Item=()>, F::Error: fmt::Debug
+{
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<(), Void> {
+        match self.future.poll() {
+            Ok(ready) => Ok(ready),
+            Err(err) => {
+                if let Some(inner) = self.inner.upgrade() {
+                    inner.borrow_mut().report_error(&err);
+                }
+                Ok(Async::Ready(()))
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A snapshot of the state of a [`Core`](struct.Core.html) taken by a
+/// [`Sampler`](struct.Sampler.html).
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    /// When the sample was taken.
+    pub at: Instant,
+    /// Number of tasks that were ready to be polled.
+    pub queue_depth: usize,
+    /// Number of spawned tasks that have not completed.
+    pub tasks: usize,
+    /// Number of turns since the sampler was started.
+    pub turns: u64,
+    /// Turns per second since the previous sample.
+    pub turn_rate: f64,
+}
+
+/// Counters published by the core for the sampler thread to read.
+#[derive(Debug, Default)]
+pub struct Counters {
+    turns: AtomicU64,
+    queue_depth: AtomicUsize,
+    tasks: AtomicUsize,
+}
+
+impl Counters {
+    /// Record the start of a turn.
+    pub fn record(&self, queue_depth: usize, tasks: usize) {
+        self.turns.fetch_add(1, Ordering::Relaxed);
+        self.queue_depth.store(queue_depth, Ordering::Relaxed);
+        self.tasks.store(tasks, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct History {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+}
+
+/// Handle to a background thread that periodically samples a
+/// [`Core`](struct.Core.html).
+///
+/// See [`Core::start_sampler`](struct.Core.html#method.start_sampler).  The
+/// thread exits once either the `Sampler` or the `Core` is dropped.
+#[derive(Debug)]
+pub struct Sampler(Arc<Mutex<History>>);
+
+impl Sampler {
+    pub(crate) fn spawn(counters: Weak<Counters>,
+                        interval: Duration,
+                        capacity: usize) -> Self {
+        let history = Arc::new(Mutex::new(History {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity,
+        }));
+        let weak = Arc::downgrade(&history);
+        thread::spawn(move || sample(counters, weak, interval));
+        Sampler(history)
+    }
+
+    /// The retained samples, oldest first.
+    pub fn history(&self) -> Vec<Sample> {
+        self.0.lock().unwrap().samples.iter().cloned().collect()
+    }
+
+    /// The most recent sample, if any.
+    pub fn latest(&self) -> Option<Sample> {
+        self.0.lock().unwrap().samples.back().cloned()
+    }
+}
+
+fn sample(counters: Weak<Counters>,
+          history: Weak<Mutex<History>>,
+          interval: Duration) {
+    let mut previous: Option<(Instant, u64)> = None;
+    loop {
+        thread::sleep(interval);
+        let (counters, history) = match (counters.upgrade(),
+                                         history.upgrade()) {
+            (Some(counters), Some(history)) => (counters, history),
+            _ => return,
+        };
+        let now = Instant::now();
+        let turns = counters.turns.load(Ordering::Relaxed);
+        let turn_rate = match previous {
+            None => 0.0,
+            Some((at, previous_turns)) => {
+                let secs = (now - at).as_secs_f64();
+                if secs > 0.0 {
+                    turns.saturating_sub(previous_turns) as f64 / secs
+                } else {
+                    0.0
+                }
+            }
+        };
+        previous = Some((now, turns));
+        let mut history = history.lock().unwrap();
+        if history.capacity == 0 {
+            continue;
+        }
+        if history.samples.len() == history.capacity {
+            history.samples.pop_front();
+        }
+        history.samples.push_back(Sample {
+            at: now,
+            queue_depth: counters.queue_depth.load(Ordering::Relaxed),
+            tasks: counters.tasks.load(Ordering::Relaxed),
+            turns: turns,
+            turn_rate: turn_rate,
+        });
+    }
+}
@@ -0,0 +1,120 @@
+//! The policies that run around every turn of a core, and around every poll
+//! of a task, kept apart from `Core::turn_with` so that each can be followed
+//! and changed on its own.
+
+use std::sync::Arc;
+use std::thread;
+use futures::{Future, Poll};
+use futures::executor::Spawn;
+use batch;
+use coop;
+use idle::Idle;
+use pacing::Pacing;
+#[cfg(feature = "watchdog")]
+use watchdog::Watchdog;
+use super::{SharedQueue, Ticket};
+
+/// A policy that takes part in every turn of a core.
+pub trait Schedulable {
+    /// Called before the turn polls anything, e.g. to wait first.
+    fn before_turn(&mut self) {}
+
+    /// Called once the turn is over, with whether it polled a task.
+    fn after_turn(&mut self, _polled: bool, _queue: &SharedQueue) {}
+}
+
+impl<S: Schedulable> Schedulable for Option<S> {
+    fn before_turn(&mut self) {
+        if let Some(ref mut policy) = *self {
+            policy.before_turn();
+        }
+    }
+
+    fn after_turn(&mut self, polled: bool, queue: &SharedQueue) {
+        if let Some(ref mut policy) = *self {
+            policy.after_turn(polled, queue);
+        }
+    }
+}
+
+/// Sleeps so that turns start at most as often as the pacing allows, and
+/// after turns that found nothing to do.
+impl Schedulable for Pacing {
+    fn before_turn(&mut self) {
+        if let Some(wait) = self.wait_before_turn() {
+            thread::sleep(wait);
+        }
+    }
+
+    fn after_turn(&mut self, polled: bool, _: &SharedQueue) {
+        if polled {
+            return;
+        }
+        if let Some(wait) = self.wait_when_idle() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Spins, yields or parks the thread after turns that found nothing to do.
+impl Schedulable for Idle {
+    fn after_turn(&mut self, polled: bool, queue: &SharedQueue) {
+        if polled {
+            self.busy();
+        } else {
+            self.idle(queue);
+        }
+    }
+}
+
+/// Times the turns for the watchdog thread.
+#[cfg(feature = "watchdog")]
+impl Schedulable for Watchdog {
+    fn before_turn(&mut self) {
+        self.start_turn();
+    }
+
+    fn after_turn(&mut self, _: bool, _: &SharedQueue) {
+        self.end_turn();
+    }
+}
+
+/// The turn policies of a core.
+#[derive(Debug, Default)]
+pub struct Schedule {
+    pub pacing: Pacing,
+    pub idle: Option<Idle>,
+    #[cfg(feature = "watchdog")]
+    pub watchdog: Option<Watchdog>,
+}
+
+/// Pacing goes first, so that the watchdog does not count its wait as part
+/// of the turn, and the idle strategy goes last, since it may park the
+/// thread until a task is woken.
+impl Schedulable for Schedule {
+    fn before_turn(&mut self) {
+        self.pacing.before_turn();
+        #[cfg(feature = "watchdog")]
+        self.watchdog.before_turn();
+    }
+
+    fn after_turn(&mut self, polled: bool, queue: &SharedQueue) {
+        #[cfg(feature = "watchdog")]
+        self.watchdog.after_turn(polled, queue);
+        self.pacing.after_turn(polled, queue);
+        self.idle.after_turn(polled, queue);
+    }
+}
+
+/// Poll the future of a task once, woken through `ticket`: its wake-ups of
+/// the tasks of `queue` are batched, and it gets a fresh coop budget.
+pub fn poll<F: Future>(spawn: &mut Spawn<F>,
+                       ticket: &Arc<Ticket>,
+                       queue: &SharedQueue) -> Poll<F::Item, F::Error> {
+    ticket.unqueue();
+    #[cfg(feature = "intercept")]
+    ticket.start_poll();
+    batch::poll_batched(queue, || {
+        coop::with_budget(|| spawn.poll_future(ticket.clone()))
+    })
+}
@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use futures::{Async, Future, IntoFuture, Poll};
+use futures::task::{self, Task};
+use void::Void;
+use super::{CancellationToken, Handle};
+
+#[derive(Default)]
+struct Shared {
+    // children that have not been dropped yet
+    running: usize,
+    // the scope future, once its own future has finished
+    waiter: Option<Task>,
+}
+
+/// Decrements the number of running children when the child is dropped,
+/// whether it finished or not.
+struct Child(Rc<RefCell<Shared>>);
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        let waiter = {
+            let mut shared = self.0.borrow_mut();
+            shared.running -= 1;
+            if shared.running > 0 {
+                return;
+            }
+            shared.waiter.take()
+        };
+        if let Some(waiter) = waiter {
+            waiter.unpark();
+        }
+    }
+}
+
+/// Spawns the children of a scope created with
+/// [`Handle::scope`](struct.Handle.html#method.scope).
+///
+/// Cloning a `Scope` yields another reference to the same scope, which can
+/// be moved into the children so that they spawn siblings.
+#[derive(Clone)]
+pub struct Scope<'a> {
+    handle: Handle<'a>,
+    token: CancellationToken,
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl<'a> fmt::Debug for Scope<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("running", &self.running())
+            .field("cancelled", &self.token.is_cancelled())
+            .finish()
+    }
+}
+
+impl<'a> Scope<'a> {
+    /// Spawn a child task.  The scope does not resolve until it has
+    /// finished or been cancelled.
+    pub fn spawn<F>(&self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'a
+    {
+        self.shared.borrow_mut().running += 1;
+        let child = Child(self.shared.clone());
+        self.handle.spawn_or_drop(self.token.cancelled()
+                                  .select(f.into_future())
+                                  .then(move |_| {
+                                      drop(child);
+                                      Ok(())
+                                  }));
+    }
+
+    /// Cancel the children that are still running.  Each of them is dropped
+    /// the next time the core gets to it, instead of being polled.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Number of children that have not finished or been dropped yet.
+    pub fn running(&self) -> usize {
+        self.shared.borrow().running
+    }
+}
+
+/// A future that resolves to the output of the future of a scope, once all
+/// the children of the scope are done.
+///
+/// Created by [`Handle::scope`](struct.Handle.html#method.scope).  If the
+/// future of the scope fails, the children are cancelled and the error is
+/// returned once they have been dropped.  Dropping a `ScopeFuture` cancels
+/// the children as well.
+#[must_use = "futures do nothing unless polled"]
+pub struct ScopeFuture<'a, F: Future> {
+    future: F,
+    result: Option<Result<F::Item, F::Error>>,
+    scope: Scope<'a>,
+}
+
+impl<'a, F: Future> ScopeFuture<'a, F> {
+    pub(crate) fn new<B, R>(handle: Handle<'a>, body: B) -> Self
+        where B: FnOnce(&Scope<'a>) -> R,
+              R: IntoFuture<Future=F, Item=F::Item, Error=F::Error>
+    {
+        let scope = Scope {
+            handle: handle,
+            token: CancellationToken::new(),
+            shared: Rc::new(RefCell::new(Shared::default())),
+        };
+        ScopeFuture {
+            future: body(&scope).into_future(),
+            result: None,
+            scope: scope,
+        }
+    }
+}
+
+impl<'a, F: Future> fmt::Debug for ScopeFuture<'a, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScopeFuture")
+            .field("done", &self.result.is_some())
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+impl<'a, F: Future> Future for ScopeFuture<'a, F> {
+    type Item = F::Item;
+    type Error = F::Error;
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        if self.result.is_none() {
+            match self.future.poll() {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::Ready(item)) => self.result = Some(Ok(item)),
+                Err(err) => {
+                    self.scope.cancel();
+                    self.result = Some(Err(err));
+                }
+            }
+        }
+        let mut shared = self.scope.shared.borrow_mut();
+        if shared.running > 0 {
+            shared.waiter = Some(task::park());
+            return Ok(Async::NotReady);
+        }
+        match self.result.take().expect("polled after completion") {
+            Ok(item) => Ok(Async::Ready(item)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<'a, F: Future> Drop for ScopeFuture<'a, F> {
+    fn drop(&mut self) {
+        self.scope.cancel();
+    }
+}
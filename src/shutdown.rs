@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+/// How [`Core::shutdown`](struct.Core.html#method.shutdown) stops a core.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// Drop the tasks right away, without running the shutdown hooks.
+    Abort,
+    /// Run the shutdown hooks one at a time, the last registered first,
+    /// while the other tasks keep running, and then drop the tasks.  Once
+    /// the given time has passed, the remaining hooks are dropped as well.
+    Drain(Duration),
+}
+
+/// How [`Core::run_with_deadline`](struct.Core.html#method.run_with_deadline)
+/// ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome<T, E> {
+    /// The future finished before the deadline, with this result.
+    Finished(Result<T, E>),
+    /// The deadline passed first, so the core was shut down.
+    Stopped {
+        /// Whether every shutdown hook ran to completion.
+        hooks_completed: bool,
+    },
+}
@@ -0,0 +1,169 @@
+//! Storage for spawned futures that avoids allocating small ones.
+
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+#[cfg(feature = "box-pool")]
+use std::ptr::NonNull;
+#[cfg(feature = "box-pool")]
+use std::rc::Rc;
+use futures::{Future, Poll};
+use void::Void;
+#[cfg(feature = "box-pool")]
+use pool::{self, BoxPool};
+
+type BoxedFuture<'a> = Box<Future<Item=(), Error=Void> + 'a>;
+
+/// Futures up to this many words are stored inline.
+const INLINE_WORDS: usize = 8;
+
+/// A type-erased future of at most `INLINE_WORDS` words, stored in place.
+struct Inline<'a> {
+    data: MaybeUninit<[usize; INLINE_WORDS]>,
+    size: usize,
+    poll: unsafe fn(*mut u8) -> Poll<(), Void>,
+    drop: unsafe fn(*mut u8),
+    // carry over the lifetime and the auto traits of the erased future
+    _marker: PhantomData<BoxedFuture<'a>>,
+}
+
+unsafe fn poll_inline<F>(data: *mut u8) -> Poll<(), Void>
+    where F: Future<Item=(), Error=Void>
+{
+    (*(data as *mut F)).poll()
+}
+
+unsafe fn drop_inline<F>(data: *mut u8) {
+    ptr::drop_in_place(data as *mut F)
+}
+
+impl<'a> Drop for Inline<'a> {
+    fn drop(&mut self) {
+        // SAFETY: `data` holds a valid value of the type `drop` was
+        // instantiated with, and it is only dropped here
+        unsafe { (self.drop)(self.data.as_mut_ptr() as *mut u8) }
+    }
+}
+
+/// A type-erased future stored in an allocation from a `BoxPool`.
+#[cfg(feature = "box-pool")]
+struct Pooled<'a> {
+    ptr: NonNull<u8>,
+    class: usize,
+    size: usize,
+    poll: unsafe fn(*mut u8) -> Poll<(), Void>,
+    drop: unsafe fn(*mut u8),
+    pool: Rc<BoxPool>,
+    _marker: PhantomData<BoxedFuture<'a>>,
+}
+
+#[cfg(feature = "box-pool")]
+impl<'a> Drop for Pooled<'a> {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` holds a valid value of the type `drop` was
+        // instantiated with, and was acquired for `class`; neither is used
+        // afterwards
+        unsafe {
+            (self.drop)(self.ptr.as_ptr());
+            self.pool.release(self.class, self.ptr);
+        }
+    }
+}
+
+enum Storage<'a> {
+    Inline(Inline<'a>),
+    #[cfg(feature = "box-pool")]
+    Pooled(Pooled<'a>),
+    Boxed(BoxedFuture<'a>),
+}
+
+/// A spawned future, stored inline if it is small enough and boxed
+/// otherwise.
+pub struct SmallBox<'a>(Storage<'a>);
+
+impl<'a> SmallBox<'a> {
+    pub fn new<F>(future: F) -> Self
+        where F: Future<Item=(), Error=Void> + 'a
+    {
+        let size = mem::size_of::<F>();
+        if size > mem::size_of::<[usize; INLINE_WORDS]>()
+            || mem::align_of::<F>() > mem::align_of::<usize>() {
+            return SmallBox(Storage::Boxed(Box::new(future)));
+        }
+        let mut data = MaybeUninit::<[usize; INLINE_WORDS]>::uninit();
+        // SAFETY: the buffer is large enough and sufficiently aligned for F,
+        // as checked above
+        unsafe { ptr::write(data.as_mut_ptr() as *mut F, future) };
+        SmallBox(Storage::Inline(Inline {
+            data: data,
+            size: size,
+            poll: poll_inline::<F>,
+            drop: drop_inline::<F>,
+            _marker: PhantomData,
+        }))
+    }
+
+    /// Like `new`, but take the allocation of a future that is too large to
+    /// be stored inline from `pool`.
+    #[cfg(feature = "box-pool")]
+    pub fn new_in<F>(future: F, pool: &Rc<BoxPool>) -> Self
+        where F: Future<Item=(), Error=Void> + 'a
+    {
+        let size = mem::size_of::<F>();
+        let inline = size <= mem::size_of::<[usize; INLINE_WORDS]>()
+            && mem::align_of::<F>() <= mem::align_of::<usize>();
+        let class = BoxPool::class(size);
+        match class {
+            Some(class) if !inline && mem::align_of::<F>() <= pool::ALIGN => {
+                let ptr = pool.acquire(class);
+                // SAFETY: allocations of the class are large enough and
+                // sufficiently aligned for F, as checked above
+                unsafe { ptr::write(ptr.as_ptr() as *mut F, future) };
+                SmallBox(Storage::Pooled(Pooled {
+                    ptr: ptr,
+                    class: class,
+                    size: size,
+                    poll: poll_inline::<F>,
+                    drop: drop_inline::<F>,
+                    pool: pool.clone(),
+                    _marker: PhantomData,
+                }))
+            }
+            _ => SmallBox::new(future),
+        }
+    }
+
+    pub fn from_box(future: BoxedFuture<'a>) -> Self {
+        SmallBox(Storage::Boxed(future))
+    }
+
+    /// Size of the future itself, not counting anything it points to.
+    pub fn size(&self) -> usize {
+        match self.0 {
+            Storage::Inline(ref inline) => inline.size,
+            #[cfg(feature = "box-pool")]
+            Storage::Pooled(ref pooled) => pooled.size,
+            Storage::Boxed(ref boxed) => mem::size_of_val(&**boxed),
+        }
+    }
+}
+
+impl<'a> Future for SmallBox<'a> {
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.0 {
+            // SAFETY: `data` holds a valid value of the type `poll` was
+            // instantiated with
+            Storage::Inline(ref mut inline) => unsafe {
+                (inline.poll)(inline.data.as_mut_ptr() as *mut u8)
+            },
+            // SAFETY: likewise for `ptr`
+            #[cfg(feature = "box-pool")]
+            Storage::Pooled(ref mut pooled) => unsafe {
+                (pooled.poll)(pooled.ptr.as_ptr())
+            },
+            Storage::Boxed(ref mut boxed) => boxed.poll(),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+use intercept::{Wake, WakeInterceptor};
+
+/// A thread other than the core's that has unparked its tasks.
+///
+/// See [`WakeSources`](struct.WakeSources.html).
+#[derive(Clone, Debug)]
+pub struct WakeSource {
+    /// The unparking thread.
+    pub thread: ThreadId,
+    /// Name of the thread, if it has one.
+    pub name: Option<String>,
+    /// Number of unparks from the thread.
+    pub wakes: u64,
+    /// When the first unpark from the thread happened.
+    pub first: Instant,
+    /// When the latest unpark from the thread happened.
+    pub last: Instant,
+}
+
+impl WakeSource {
+    /// Average number of unparks per second between the first and latest
+    /// ones.
+    pub fn rate(&self) -> f64 {
+        let elapsed = self.last - self.first;
+        let secs = elapsed.as_secs() as f64
+            + elapsed.subsec_nanos() as f64 * 1e-9;
+        if secs == 0.0 {
+            0.0
+        } else {
+            (self.wakes - 1) as f64 / secs
+        }
+    }
+}
+
+/// Counts the unparks of a core's tasks that come from other threads, per
+/// thread.
+///
+/// Created by
+/// [`Core::record_wake_sources`](struct.Core.html#method.record_wake_sources).
+#[derive(Clone, Debug)]
+pub struct WakeSources {
+    core_thread: ThreadId,
+    sources: Arc<Mutex<HashMap<ThreadId, WakeSource>>>,
+}
+
+impl Default for WakeSources {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WakeSources {
+    /// Count the unparks from threads other than the current one.
+    pub fn new() -> Self {
+        WakeSources {
+            core_thread: thread::current().id(),
+            sources: Default::default(),
+        }
+    }
+
+    /// The threads that have unparked tasks so far, the most frequent first.
+    pub fn sources(&self) -> Vec<WakeSource> {
+        let mut sources: Vec<_> = self.sources.lock().unwrap().values()
+            .cloned()
+            .collect();
+        sources.sort_by_key(|source| Reverse(source.wakes));
+        sources
+    }
+
+    /// Total number of unparks from other threads.
+    pub fn total(&self) -> u64 {
+        self.sources.lock().unwrap().values().map(|source| source.wakes).sum()
+    }
+}
+
+impl WakeInterceptor for WakeSources {
+    fn on_unpark(&self, wake: &mut Wake) {
+        if wake.thread() == self.core_thread {
+            return;
+        }
+        let mut sources = self.sources.lock().unwrap();
+        let source = sources.entry(wake.thread()).or_insert_with(|| {
+            WakeSource {
+                thread: wake.thread(),
+                name: thread::current().name().map(String::from),
+                wakes: 0,
+                first: wake.at(),
+                last: wake.at(),
+            }
+        });
+        source.wakes += 1;
+        source.last = wake.at();
+    }
+
+    fn on_poll(&self, _: &Wake, _: Duration) {}
+}
@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::fmt;
+
+/// Why a future could not be spawned.  The future is handed back.
+pub enum SpawnError<F> {
+    /// The core has been dropped.
+    Shutdown(F),
+    /// The core already has as many tasks as its limit allows.
+    Full(F),
+    /// The core holds as many tasks as its ids can tell apart.  See
+    /// [`TaskId`](struct.TaskId.html).
+    IdsExhausted(F),
+}
+
+impl<F> SpawnError<F> {
+    /// Recover the future that could not be spawned.
+    pub fn into_inner(self) -> F {
+        match self {
+            SpawnError::Shutdown(f) |
+            SpawnError::Full(f) |
+            SpawnError::IdsExhausted(f) => f,
+        }
+    }
+}
+
+impl<F> fmt::Debug for SpawnError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpawnError::Shutdown(_) => f.debug_tuple("Shutdown").finish(),
+            SpawnError::Full(_) => f.debug_tuple("Full").finish(),
+            SpawnError::IdsExhausted(_) => {
+                f.debug_tuple("IdsExhausted").finish()
+            }
+        }
+    }
+}
+
+impl<F> fmt::Display for SpawnError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpawnError::Shutdown(_) => f.write_str("core was dropped"),
+            SpawnError::Full(_) => f.write_str("core is at its task limit"),
+            SpawnError::IdsExhausted(_) => {
+                f.write_str("core has run out of task ids")
+            }
+        }
+    }
+}
+
+impl<F> Error for SpawnError<F> {}
@@ -1,9 +1,10 @@
 use std::{fmt, mem};
-use futures::{Async, Future, Poll};
+use std::any::Any;
+use futures::{Async, Future, IntoFuture, Poll};
 use futures::task::{self, Task};
 use void::Void;
 use super::drop_off;
-use super::Handle;
+use super::{AbortHandle, Handle, JoinError};
 
 struct SpawnedFuture<F: Future> {
     future: F,
@@ -42,9 +43,22 @@ impl<F: Future> Future for SpawnedFuture<F> {
     }
 }
 
+impl<F: Future> Drop for SpawnedFuture<F> {
+    fn drop(&mut self) {
+        // dropped before it finished, so the `SpawnFuture` has to be told
+        if self.sender.take().is_some() {
+            self.task.unpark();
+        }
+    }
+}
+
 enum State<'a, F: Future> {
-    Starting { handle: Handle<'a>, future: F },
-    Waiting { receiver: drop_off::Receiver<Result<F::Item, F::Error>> },
+    // inline: whether to poll the future in place before spawning it
+    Starting { handle: Handle<'a>, future: F, inline: bool },
+    Waiting {
+        receiver: drop_off::Receiver<Result<F::Item, F::Error>>,
+        abort: Option<AbortHandle>,
+    },
     Invalid,
 }
 
@@ -55,15 +69,17 @@ impl<'a, F> fmt::Debug for State<'a, F>
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            &State::Starting { ref handle, ref future } => {
+            &State::Starting { ref handle, ref future, inline } => {
                 f.debug_struct("State::Starting")
                     .field("handle", handle)
                     .field("future", future)
+                    .field("inline", &inline)
                     .finish()
             }
-            &State::Waiting { ref receiver } => {
+            &State::Waiting { ref receiver, ref abort } => {
                 f.debug_struct("State::Waiting")
                     .field("receiver", receiver)
+                    .field("abort", abort)
                     .finish()
             }
             &State::Invalid => {
@@ -74,12 +90,63 @@ impl<'a, F> fmt::Debug for State<'a, F>
     }
 }
 
+/// A future that spawns another future as its own task the first time it is
+/// polled, and then resolves to the output of that task.
+///
+/// Created by [`Handle::spawn_future`](struct.Handle.html#method.spawn_future)
+/// or [`spawn_future_inline`](struct.Handle.html#method.spawn_future_inline).
+/// Dropping a `SpawnFuture` aborts the task, unless it was
+/// [detached](#method.detach).
+///
+/// Like a [`JoinHandle`](struct.JoinHandle.html), it fails with
+/// `JoinError::Canceled` if the task is dropped before it finishes, e.g.
+/// because the core was reset or shut down.
 #[must_use = "futures do nothing unless polled"]
 pub struct SpawnFuture<'a, F: Future>(State<'a, F>);
 
 impl<'a, F: Future> SpawnFuture<'a, F> {
-    pub fn new(handle: Handle<'a>, future: F) -> Self {
-        SpawnFuture(State::Starting { handle: handle, future: future })
+    pub fn new<T>(handle: Handle<'a>, future: T) -> Self
+        where T: IntoFuture<Future=F, Item=F::Item, Error=F::Error>
+    {
+        SpawnFuture(State::Starting {
+            handle: handle,
+            future: future.into_future(),
+            inline: false,
+        })
+    }
+
+    pub(crate) fn inline<T>(handle: Handle<'a>, future: T) -> Self
+        where T: IntoFuture<Future=F, Item=F::Item, Error=F::Error>
+    {
+        SpawnFuture(State::Starting {
+            handle: handle,
+            future: future.into_future(),
+            inline: true,
+        })
+    }
+}
+
+impl<'a, F: Future + 'a> SpawnFuture<'a, F> {
+    /// Let the task run to completion on its own, even though its output is
+    /// no longer wanted.  The task is spawned now if it has not been yet.
+    pub fn detach(mut self) {
+        match mem::replace(&mut self.0, State::Invalid) {
+            State::Starting { handle, future, .. } => {
+                handle.spawn_or_drop(future.then(|_| Ok(())));
+            }
+            State::Waiting { abort, .. } => mem::drop(abort),
+            State::Invalid => {}
+        }
+    }
+
+    /// Erase the types of the item and error, so that futures spawned with
+    /// different types can be kept together.  The error is the
+    /// `JoinError<F::Error>`.
+    pub fn into_any(self) -> AnyJoinHandle<'a>
+        where F::Item: 'static, F::Error: 'static
+    {
+        AnyJoinHandle(Box::new(self.map(|item| Box::new(item) as Box<Any>)
+                               .map_err(|err| Box::new(err) as Box<Any>)))
     }
 }
 
@@ -97,30 +164,76 @@ impl<'a, F> fmt::Debug for SpawnFuture<'a, F>
 
 impl<'a, F: Future + 'a> Future for SpawnFuture<'a, F> {
     type Item = F::Item;
-    type Error = F::Error;
+    type Error = JoinError<F::Error>;
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         match mem::replace(&mut self.0, State::Invalid) {
-            State::Starting { handle, future } => {
+            State::Starting { handle, mut future, inline } => {
+                if inline {
+                    // a future that is ready right away is not worth a task
+                    // of its own, nor the turns it takes to hand over the
+                    // output
+                    match future.poll() {
+                        Ok(Async::NotReady) => {}
+                        ready => return ready.map_err(JoinError::Failed),
+                    }
+                }
                 let (sender, receiver) = drop_off::new();
-                handle.spawn(SpawnedFuture {
+                let (abort, registration) = AbortHandle::new_pair();
+                handle.spawn_abortable(SpawnedFuture {
                     future: future,
                     sender: Some(sender),
                     task: task::park(),
-                });
-                self.0 = State::Waiting { receiver: receiver };
+                }, registration);
+                self.0 = State::Waiting {
+                    receiver: receiver,
+                    abort: Some(abort),
+                };
                 Ok(Async::NotReady)
             }
-            State::Waiting { receiver } => match receiver.take() {
+            State::Waiting { receiver, abort } => match receiver.take() {
                 Ok(Ok(item)) => Ok(Async::Ready(item)),
-                Ok(Err(err)) => Err(err),
+                Ok(Err(err)) => Err(JoinError::Failed(err)),
                 Err(Some(receiver)) => {
                     // spurious wake-up
-                    self.0 = State::Waiting { receiver: receiver };
+                    self.0 = State::Waiting {
+                        receiver: receiver,
+                        abort: abort,
+                    };
                     Ok(Async::NotReady)
                 }
-                Err(None) => panic!("SpawnedFuture was dropped"),
+                Err(None) => Err(JoinError::Canceled),
             },
             State::Invalid => panic!("invalid State"),
         }
     }
 }
+
+impl<'a, F: Future> Drop for SpawnFuture<'a, F> {
+    fn drop(&mut self) {
+        if let State::Waiting { abort: Some(ref abort), .. } = self.0 {
+            abort.abort();
+        }
+    }
+}
+
+/// A [`SpawnFuture`](struct.SpawnFuture.html) whose item and error types
+/// have been erased by [`into_any`](struct.SpawnFuture.html#method.into_any).
+///
+/// The item or error can be recovered with `Box::<Any>::downcast`.
+#[must_use = "futures do nothing unless polled"]
+pub struct AnyJoinHandle<'a>(Box<Future<Item=Box<Any>, Error=Box<Any>> + 'a>);
+
+impl<'a> fmt::Debug for AnyJoinHandle<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("AnyJoinHandle")
+            .finish()
+    }
+}
+
+impl<'a> Future for AnyJoinHandle<'a> {
+    type Item = Box<Any>;
+    type Error = Box<Any>;
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.0.poll()
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+use futures::{Async, Future, IntoFuture, Poll};
+use super::{Handle, SpawnError};
+
+/// A future that spawns a task once its core has room for it.
+///
+/// Created by
+/// [`Handle::spawn_when_ready`](struct.Handle.html#method.spawn_when_ready).
+/// It resolves once the task is spawned, or fails with
+/// `SpawnError::Shutdown` if the core is dropped first.
+#[must_use = "futures do nothing unless polled"]
+pub struct SpawnWhenReady<'a, F> {
+    handle: Handle<'a>,
+    future: Option<F>,
+}
+
+impl<'a, F> SpawnWhenReady<'a, F> {
+    pub(crate) fn new(handle: Handle<'a>, f: F) -> Self {
+        SpawnWhenReady {
+            handle: handle,
+            future: Some(f),
+        }
+    }
+}
+
+impl<'a, F> fmt::Debug for SpawnWhenReady<'a, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SpawnWhenReady")
+            .field("spawned", &self.future.is_none())
+            .finish()
+    }
+}
+
+impl<'a, F> Future for SpawnWhenReady<'a, F>
+    where F: IntoFuture<Item=()>, F::Future: 'a, F::Error: fmt::Debug + 'a
+{
+    type Item = ();
+    type Error = SpawnError<F>;
+    fn poll(&mut self) -> Poll<(), SpawnError<F>> {
+        let f = self.future.take().expect("polled after completion");
+        match self.handle.spawn(f) {
+            Ok(()) => Ok(Async::Ready(())),
+            Err(SpawnError::Full(f)) => {
+                self.future = Some(f);
+                self.handle.wait_for_capacity();
+                Ok(Async::NotReady)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+use futures::{Poll, Stream};
+
+/// A stream that calls a closure to poll for its next item.
+///
+/// Created by [`stream_fn`](fn.stream_fn.html).
+#[must_use = "streams do nothing unless polled"]
+pub struct StreamFn<F>(F);
+
+impl<F> fmt::Debug for StreamFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("StreamFn")
+            .finish()
+    }
+}
+
+impl<T, E, F> Stream for StreamFn<F>
+    where F: FnMut() -> Poll<Option<T>, E>
+{
+    type Item = T;
+    type Error = E;
+    fn poll(&mut self) -> Poll<Option<T>, E> {
+        (self.0)()
+    }
+}
+
+/// Create a stream whose `poll` calls `f`.
+///
+/// `f` follows the contract of `Stream::poll`: it returns `Ready(Some(item))`
+/// for each item and `Ready(None)` once the stream has ended.  When it
+/// returns `NotReady`, it must first have arranged for the current task to be
+/// unparked once an item may be available, either by polling something that
+/// returned `NotReady` itself or by handing out `futures::task::park()`.
+/// Otherwise the task is never polled again.  To simply try again on the
+/// next turn, call `task::park().unpark()` before returning `NotReady`.
+pub fn stream_fn<T, E, F>(f: F) -> StreamFn<F>
+    where F: FnMut() -> Poll<Option<T>, E>
+{
+    StreamFn(f)
+}
+
+/// A stream that polls for its next item with a closure and some state.
+///
+/// Created by [`unfold_local`](fn.unfold_local.html).
+#[must_use = "streams do nothing unless polled"]
+pub struct UnfoldLocal<S, F> {
+    state: S,
+    f: F,
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for UnfoldLocal<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UnfoldLocal")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl<T, E, S, F> Stream for UnfoldLocal<S, F>
+    where F: FnMut(&mut S) -> Poll<Option<T>, E>
+{
+    type Item = T;
+    type Error = E;
+    fn poll(&mut self) -> Poll<Option<T>, E> {
+        (self.f)(&mut self.state)
+    }
+}
+
+/// Create a stream whose `poll` calls `f` with a mutable reference to
+/// `state`.
+///
+/// This is [`stream_fn`](fn.stream_fn.html) with the state kept by the
+/// stream instead of being captured by the closure, which saves an
+/// `Rc<RefCell<_>>` when the state is needed elsewhere afterwards: it can be
+/// recovered with [`into_state`](struct.UnfoldLocal.html#method.into_state).
+/// The same contract for returning `NotReady` applies.
+///
+/// Unlike `futures::stream::unfold`, the state is never moved in and out of
+/// a future, so no future needs to be created per item.
+pub fn unfold_local<T, E, S, F>(state: S, f: F) -> UnfoldLocal<S, F>
+    where F: FnMut(&mut S) -> Poll<Option<T>, E>
+{
+    UnfoldLocal {
+        state: state,
+        f: f,
+    }
+}
+
+impl<S, F> UnfoldLocal<S, F> {
+    /// Recover the state of the stream.
+    pub fn into_state(self) -> S {
+        self.state
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+use futures::{Async, Future, IntoFuture, Poll};
+use futures::task;
+
+/// When a supervised task is restarted.  See
+/// [`RestartPolicy`](struct.RestartPolicy.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Restart {
+    /// Never: the task finishes the first time its future does.
+    Never,
+    /// When its future fails.
+    OnError,
+    /// Whenever its future finishes, whether it succeeded or failed.
+    Always,
+}
+
+/// How [`Handle::supervise`](struct.Handle.html#method.supervise) restarts a
+/// task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// When to restart.  Defaults to `Restart::OnError`.
+    pub restart: Restart,
+    /// Give up after restarting this many times.  Defaults to `None`, which
+    /// means no limit.
+    pub max_restarts: Option<u32>,
+    /// How long to wait before each restart.  Defaults to zero.
+    ///
+    /// The core has no timers, so the supervisor waits by yielding to the
+    /// other tasks on every turn until the delay has passed, which keeps the
+    /// core busy meanwhile.
+    pub backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            restart: Restart::OnError,
+            max_restarts: None,
+            backoff: Duration::from_millis(0),
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn wants_restart<T, E>(&self, result: &Result<T, E>, restarts: u32)
+                           -> bool {
+        let restart = match self.restart {
+            Restart::Never => false,
+            Restart::OnError => result.is_err(),
+            Restart::Always => true,
+        };
+        match self.max_restarts {
+            Some(max) => restart && restarts < max,
+            None => restart,
+        }
+    }
+}
+
+enum State<F> {
+    Running(F),
+    // waiting to restart until then
+    Backoff(Instant),
+}
+
+/// The future that is actually spawned by
+/// [`Handle::supervise`](struct.Handle.html#method.supervise): it runs the
+/// futures made by `factory` one after the other, as the policy allows.
+pub struct Supervised<M, R: IntoFuture> {
+    factory: M,
+    policy: RestartPolicy,
+    restarts: u32,
+    state: State<R::Future>,
+}
+
+impl<M, R> Supervised<M, R>
+    where M: FnMut() -> R, R: IntoFuture
+{
+    pub fn new(policy: RestartPolicy, mut factory: M) -> Self {
+        let future = factory().into_future();
+        Supervised {
+            factory: factory,
+            policy: policy,
+            restarts: 0,
+            state: State::Running(future),
+        }
+    }
+}
+
+impl<M, R> Future for Supervised<M, R>
+    where M: FnMut() -> R, R: IntoFuture
+{
+    type Item = R::Item;
+    type Error = R::Error;
+    fn poll(&mut self) -> Poll<R::Item, R::Error> {
+        loop {
+            let restart_at = match self.state {
+                State::Running(ref mut future) => {
+                    let result = match future.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Ok(Async::Ready(item)) => Ok(item),
+                        Err(err) => Err(err),
+                    };
+                    if !self.policy.wants_restart(&result, self.restarts) {
+                        return result.map(Async::Ready);
+                    }
+                    self.restarts += 1;
+                    // let the other tasks run before restarting, even
+                    // without a backoff
+                    self.state = State::Backoff(Instant::now()
+                                                + self.policy.backoff);
+                    task::park().unpark();
+                    return Ok(Async::NotReady);
+                }
+                State::Backoff(at) => at,
+            };
+            if Instant::now() < restart_at {
+                task::park().unpark();
+                return Ok(Async::NotReady);
+            }
+            self.state = State::Running((self.factory)().into_future());
+        }
+    }
+}
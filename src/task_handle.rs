@@ -0,0 +1,43 @@
+use std::fmt;
+use std::sync::Arc;
+use futures::executor::Unpark;
+use super::TaskId;
+
+/// Starts a task that was spawned paused.
+///
+/// Created by [`Handle::spawn_paused`](struct.Handle.html#method.spawn_paused).
+/// The handle can be sent to another thread, so that tasks staged in bulk
+/// can be released together, such as at a synchronized start time.  A task
+/// whose handle is dropped without being started is never polled, and is
+/// dropped along with its core.
+pub struct TaskHandle {
+    // None if the core was gone when the task was spawned
+    ticket: Option<(Arc<Unpark>, TaskId)>,
+}
+
+impl fmt::Debug for TaskHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskHandle")
+            .field("id", &self.id())
+            .finish()
+    }
+}
+
+impl TaskHandle {
+    pub(crate) fn new(ticket: Option<(Arc<Unpark>, TaskId)>) -> Self {
+        TaskHandle { ticket: ticket }
+    }
+
+    /// The id of the task, or `None` if its core was gone when it was
+    /// spawned.
+    pub fn id(&self) -> Option<TaskId> {
+        self.ticket.as_ref().map(|&(_, id)| id)
+    }
+
+    /// Queue the task, so that its core polls it on a later turn.
+    pub fn start(self) {
+        if let Some((ticket, _)) = self.ticket {
+            ticket.unpark();
+        }
+    }
+}
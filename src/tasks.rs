@@ -0,0 +1,87 @@
+use std::borrow::Cow;
+use std::time::Duration;
+use super::TaskId;
+
+/// Whether a task is waiting to be polled or to be woken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TaskState {
+    /// The task is in the ready queue.
+    Queued,
+    /// The task is waiting to be woken.
+    Idle,
+}
+
+/// Information about a task that has not finished, as listed by
+/// [`Core::tasks`](struct.Core.html#method.tasks).
+#[derive(Clone, Debug)]
+pub struct TaskRef {
+    id: TaskId,
+    name: Option<Cow<'static, str>>,
+    state: TaskState,
+    age: Duration,
+    polls: u64,
+    size: usize,
+    wakers: usize,
+}
+
+impl TaskRef {
+    pub(crate) fn new(id: TaskId,
+                      name: Option<Cow<'static, str>>,
+                      state: TaskState,
+                      age: Duration,
+                      polls: u64,
+                      size: usize,
+                      wakers: usize) -> Self {
+        TaskRef {
+            id: id,
+            name: name,
+            state: state,
+            age: age,
+            polls: polls,
+            size: size,
+            wakers: wakers,
+        }
+    }
+
+    /// Id of the task.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Name of the task, if it was spawned with
+    /// [`Handle::spawn_named`](struct.Handle.html#method.spawn_named).
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Whether the task is queued or idle.
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+
+    /// Time elapsed since the task was spawned.
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+
+    /// Number of times the task has been polled.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Size of the task's future in bytes, not counting anything it points
+    /// to.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of handles outside of the core that can wake the task, such
+    /// as the `Task`s it handed out through `futures::task::park()`.
+    ///
+    /// An idle task without any can only be woken by
+    /// [`Core::poke`](struct.Core.html#method.poke), so it is likely stuck
+    /// for good.
+    pub fn wakers(&self) -> usize {
+        self.wakers
+    }
+}
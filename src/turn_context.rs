@@ -0,0 +1,180 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+use futures::{Future, IntoFuture};
+use void::Void;
+use super::{OwnedHandle, TaskId};
+
+type DeferredBox = Box<Future<Item=(), Error=Void>>;
+
+thread_local! {
+    // the context of the core polling a task on this thread, or null
+    static CURRENT: Cell<*const TurnContext> = Cell::new(ptr::null());
+}
+
+/// Schedules work on behalf of the task being polled, from code that has no
+/// access to the core, such as callbacks invoked by a C library.
+///
+/// Obtained with
+/// [`Core::with_turn_context`](struct.Core.html#method.with_turn_context).
+/// Nothing is done right away: the core applies the requests once the poll
+/// returns, so they can be made while the core or its tasks are borrowed.
+pub struct TurnContext {
+    task: Cell<TaskId>,
+    // only set for cores that enabled Handle::current
+    handle: RefCell<Option<OwnedHandle>>,
+    // the values set with Core::set_context, by type
+    contexts: RefCell<HashMap<TypeId, Rc<Any>>>,
+    spawns: RefCell<Vec<DeferredBox>>,
+    wakes: RefCell<Vec<TaskId>>,
+}
+
+impl fmt::Debug for TurnContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TurnContext")
+            .field("task", &self.task.get())
+            .field("handle", &self.handle.borrow().is_some())
+            .field("contexts", &self.contexts.borrow().len())
+            .field("spawns", &self.spawns.borrow().len())
+            .field("wakes", &self.wakes.borrow())
+            .finish()
+    }
+}
+
+impl Default for TurnContext {
+    fn default() -> Self {
+        TurnContext {
+            task: Cell::new(TaskId::main()),
+            handle: RefCell::new(None),
+            contexts: RefCell::new(HashMap::new()),
+            spawns: RefCell::new(Vec::new()),
+            wakes: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl TurnContext {
+    pub(crate) fn set_handle(&self, handle: OwnedHandle) {
+        *self.handle.borrow_mut() = Some(handle);
+    }
+
+    pub(crate) fn renew_handle(&self, generation: u64) {
+        if let Some(ref mut handle) = *self.handle.borrow_mut() {
+            handle.2 = generation;
+        }
+    }
+
+    pub(crate) fn set_context(&self, type_id: TypeId, value: Rc<Any>) {
+        self.contexts.borrow_mut().insert(type_id, value);
+    }
+
+    pub(crate) fn handle(&self) -> Option<OwnedHandle> {
+        self.handle.borrow().clone()
+    }
+
+    /// The task being polled.
+    pub fn task(&self) -> TaskId {
+        self.task.get()
+    }
+
+    /// Spawn a future on the core once the poll returns.
+    pub fn spawn<F>(&self, f: F)
+        where F: IntoFuture<Item=(), Error=Void>, F::Future: 'static
+    {
+        self.spawns.borrow_mut().push(Box::new(f.into_future()));
+    }
+
+    /// Wake a task of the core once the poll returns, as
+    /// [`Core::poke`](struct.Core.html#method.poke) does.  Waking the task
+    /// being polled makes the core poll it again on a later turn.
+    pub fn wake(&self, id: TaskId) {
+        self.wakes.borrow_mut().push(id);
+    }
+}
+
+/// What was requested through the context during a poll.
+pub struct Deferred {
+    pub spawns: Vec<DeferredBox>,
+    pub wakes: Vec<TaskId>,
+}
+
+/// Restores the context of the enclosing poll, if polls are nested.
+struct Restore<'c> {
+    context: &'c TurnContext,
+    task: TaskId,
+    saved: *const TurnContext,
+}
+
+impl<'c> Drop for Restore<'c> {
+    fn drop(&mut self) {
+        self.context.task.set(self.task);
+        let saved = self.saved;
+        let _ = CURRENT.try_with(|current| current.set(saved));
+    }
+}
+
+/// The requests pushed to `requests` after its first `from` entries, which
+/// belong to an enclosing poll of the same core.
+fn split_off<T>(requests: &RefCell<Vec<T>>, from: usize) -> Vec<T> {
+    let mut requests = requests.borrow_mut();
+    if from == 0 {
+        mem::take(&mut *requests)
+    } else {
+        requests.split_off(from)
+    }
+}
+
+/// Call `poll` with `context` as the current context, polling `task`, and
+/// return what was requested through it.
+pub fn poll_in<R, F>(context: &TurnContext, task: TaskId, poll: F)
+                     -> (R, Deferred)
+    where F: FnOnce() -> R
+{
+    let spawns = context.spawns.borrow().len();
+    let wakes = context.wakes.borrow().len();
+    let result = {
+        let _restore = Restore {
+            context: context,
+            task: context.task.replace(task),
+            saved: CURRENT.with(|current| current.replace(context)),
+        };
+        poll()
+    };
+    let deferred = Deferred {
+        spawns: split_off(&context.spawns, spawns),
+        wakes: split_off(&context.wakes, wakes),
+    };
+    (result, deferred)
+}
+
+/// Call `f` with the context of the task being polled, if any.
+pub fn with<R, F: FnOnce(&TurnContext) -> R>(f: F) -> Option<R> {
+    let current = CURRENT.try_with(Cell::get).unwrap_or(ptr::null());
+    // the context is only set for the duration of `poll_in`, during which
+    // the caller keeps it alive
+    unsafe { current.as_ref() }.map(f)
+}
+
+/// The id of the task that is being polled by a core on this thread, or
+/// `None` outside of a poll.  The future passed to
+/// [`Core::run`](struct.Core.html#method.run) has the id `TaskId::main()`.
+pub fn current_task_id() -> Option<TaskId> {
+    with(TurnContext::task)
+}
+
+/// The value of type `T` that the core polling the current task was given
+/// with [`Core::set_context`](struct.Core.html#method.set_context), or
+/// `None` if it has none or if no task is being polled.
+pub fn context<T: 'static>() -> Option<Rc<T>> {
+    with(|context| {
+        context.contexts.borrow().get(&TypeId::of::<T>()).cloned()
+    })
+        .and_then(|value| value)
+        .map(|value| {
+            value.downcast::<T>().expect("context of the wrong type")
+        })
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use futures::{Async, Future, Poll, Stream, task};
+use futures::task::{EventSet, UnparkEvent};
+use index_queue::IndexQueue;
+use vec_arena::Arena;
+
+/// Indices of the sub-futures that have been woken.
+#[derive(Debug, Default)]
+struct ReadySet(Mutex<IndexQueue>);
+
+impl EventSet for ReadySet {
+    fn insert(&self, id: usize) {
+        self.0.lock().unwrap().push_back(id);
+    }
+}
+
+/// A set of futures that are all polled within a single task and yielded as
+/// a `Stream` in the order they complete.
+///
+/// This is much cheaper than spawning each future as a separate task.  The
+/// futures need not be `Send`: only the set of woken indices is shared with
+/// wakers, because `futures` requires unpark events to be `Send + Sync`.
+/// Each sub-future is only polled when it was woken itself.
+///
+/// The stream ends when the set is empty.  An error from a sub-future is
+/// yielded as the stream error and the remaining futures are left in the
+/// set.
+#[must_use = "streams do nothing unless polled"]
+pub struct LocalFuturesUnordered<F> {
+    futures: Arena<F>,
+    ready: Arc<ReadySet>,
+}
+
+impl<F> Default for LocalFuturesUnordered<F> {
+    fn default() -> Self {
+        LocalFuturesUnordered {
+            futures: Arena::new(),
+            ready: Default::default(),
+        }
+    }
+}
+
+impl<F> fmt::Debug for LocalFuturesUnordered<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalFuturesUnordered")
+            .field("len", &self.futures.len())
+            .field("ready", &self.ready)
+            .finish()
+    }
+}
+
+impl<F: Future> LocalFuturesUnordered<F> {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of futures that have yet to complete.
+    pub fn len(&self) -> usize {
+        self.futures.len()
+    }
+
+    /// Whether all the futures have completed.
+    pub fn is_empty(&self) -> bool {
+        self.futures.is_empty()
+    }
+
+    /// Add a future to the set.  It will be polled the next time the set is
+    /// polled.
+    pub fn push(&mut self, future: F) {
+        let index = self.futures.insert(future);
+        self.ready.insert(index);
+    }
+}
+
+impl<F: Future> Stream for LocalFuturesUnordered<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // bound the work done in one poll so that sub-futures that keep
+        // waking themselves cannot starve the other tasks on the core
+        let mut budget = self.futures.len();
+        loop {
+            if self.futures.is_empty() {
+                return Ok(Async::Ready(None));
+            }
+            let popped = self.ready.0.lock().unwrap().pop_front();
+            let index = match popped {
+                None => return Ok(Async::NotReady),
+                Some(index) => index,
+            };
+            if self.futures.get(index).is_none() {
+                // stale wake-up of a future that has already completed
+                continue;
+            }
+            if budget == 0 {
+                self.ready.insert(index);
+                task::park().unpark();
+                return Ok(Async::NotReady);
+            }
+            budget -= 1;
+            let event = UnparkEvent::new(self.ready.clone(), index);
+            let future = &mut self.futures[index];
+            let poll = task::with_unpark_event(event, || future.poll());
+            let result = match poll {
+                Ok(Async::NotReady) => continue,
+                Ok(Async::Ready(item)) => Ok(Async::Ready(Some(item))),
+                Err(err) => Err(err),
+            };
+            self.futures.remove(index);
+            self.ready.0.lock().unwrap().remove(index);
+            return result;
+        }
+    }
+}
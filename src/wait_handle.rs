@@ -0,0 +1,111 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures::{Async, Future, Poll};
+use futures::task;
+use void::Void;
+use idle;
+
+/// An OS primitive, or anything else outside the executor, that can be
+/// signaled, such as an eventfd counter, a futex word or a Windows event.
+///
+/// See [`WaitHandle`](struct.WaitHandle.html).
+pub trait Signal: Send + Sync + 'static {
+    /// Check without blocking whether the primitive has been signaled, and
+    /// if so consume the signal and return `true`.  This should be cheap:
+    /// in busy mode it is called on every turn.
+    fn try_wait(&self) -> bool;
+
+    /// Block the calling thread until the primitive is signaled, without
+    /// consuming the signal.  Returning early is allowed.
+    ///
+    /// Only used in blocking mode, where it is called from the thread of the
+    /// core in place of parking it.
+    fn wait(&self);
+}
+
+impl<S: Signal> Signal for Arc<S> {
+    fn try_wait(&self) -> bool {
+        (**self).try_wait()
+    }
+
+    fn wait(&self) {
+        (**self).wait()
+    }
+}
+
+/// A future that resolves once a [`Signal`](trait.Signal.html) is
+/// signaled, as an escape hatch for wake sources that cannot unpark a task
+/// themselves.
+///
+/// In busy mode, the signal is checked each time the core gets to the task,
+/// which is on every turn.  In blocking mode, once the
+/// [`IdleStrategy`](struct.IdleStrategy.html) of the core gets to parking its
+/// thread, the core waits on the signal instead and then wakes the task.
+/// Tasks woken by other threads in the meantime are only polled once the
+/// signal arrives or [`Signal::wait`](trait.Signal.html#tymethod.wait)
+/// returns early, so `wait` should time out if they must not be delayed.  A
+/// core without an idle strategy checks the signal on every turn, as in busy
+/// mode.
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitHandle<S> {
+    signal: Arc<S>,
+    blocking: bool,
+    // whether the idle strategy of the core is to wait for the signal
+    watching: Arc<AtomicBool>,
+}
+
+impl<S: Signal> WaitHandle<S> {
+    /// Wait for `signal` by checking it on every turn.
+    pub fn busy(signal: S) -> Self {
+        WaitHandle::new(signal, false)
+    }
+
+    /// Wait for `signal` in place of parking the thread of the core.
+    pub fn blocking(signal: S) -> Self {
+        WaitHandle::new(signal, true)
+    }
+
+    fn new(signal: S, blocking: bool) -> Self {
+        WaitHandle {
+            signal: Arc::new(signal),
+            blocking: blocking,
+            watching: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// The signal being waited for.
+    pub fn signal(&self) -> &S {
+        &self.signal
+    }
+}
+
+impl<S> fmt::Debug for WaitHandle<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WaitHandle")
+            .field("blocking", &self.blocking)
+            .field("watching", &self.watching.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<S: Signal> Future for WaitHandle<S> {
+    type Item = ();
+    type Error = Void;
+    fn poll(&mut self) -> Poll<(), Void> {
+        if self.signal.try_wait() {
+            return Ok(Async::Ready(()));
+        }
+        let task = task::park();
+        if !self.blocking {
+            task.unpark();
+        } else if !self.watching.swap(true, Ordering::SeqCst) {
+            let signal = self.signal.clone();
+            if !idle::wait_when_idle(signal, &self.watching, task.clone()) {
+                self.watching.store(false, Ordering::SeqCst);
+                task.unpark();
+            }
+        }
+        Ok(Async::NotReady)
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use std::task::{Wake, Waker};
+use futures::task::{self, Task};
+
+struct TaskWaker(Task);
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Obtain a `std::task::Waker` that unparks the current task.
+///
+/// This allows `std::future::Future`s to be polled from within a `futures`
+/// 0.1 task running on the [`Core`](struct.Core.html).  Waking the waker has
+/// the same effect as unparking the task through `futures::task::park()`.
+///
+/// # Panics
+///
+/// Panics if called outside of a task.
+///
+/// # Example
+///
+/// ```
+/// extern crate futures;
+/// extern crate synchrotron;
+///
+/// use std::future::{self, Future};
+/// use std::task::{Context, Poll};
+/// use futures::Async;
+///
+/// let mut ready = future::ready(42);
+/// let mut core = synchrotron::Core::default();
+/// let item = core.run(futures::future::poll_fn(|| {
+///     let waker = synchrotron::current_waker();
+///     let mut cx = Context::from_waker(&waker);
+///     match std::pin::Pin::new(&mut ready).poll(&mut cx) {
+///         Poll::Ready(item) => Ok::<_, ()>(Async::Ready(item)),
+///         Poll::Pending => Ok(Async::NotReady),
+///     }
+/// }));
+/// assert_eq!(item, Ok(42));
+/// ```
+pub fn current_waker() -> Waker {
+    Waker::from(Arc::new(TaskWaker(task::park())))
+}
@@ -0,0 +1,84 @@
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Information passed periodically to the watchdog callback.
+///
+/// See [`Core::set_watchdog`](struct.Core.html#method.set_watchdog).
+#[derive(Clone, Debug)]
+pub struct WatchdogReport {
+    /// Time elapsed since the last turn of the executor loop completed.
+    pub since_last_turn: Duration,
+    /// How long the turn currently in progress has been running, if any.
+    pub current_turn: Option<Duration>,
+    /// Duration of the longest turn completed since the previous report.
+    pub longest_turn: Duration,
+}
+
+#[derive(Debug)]
+struct State {
+    last_turn: Instant,
+    turn_started: Option<Instant>,
+    longest_turn: Duration,
+}
+
+/// The core's end of a watchdog.  Dropping it stops the watchdog thread.
+#[derive(Debug)]
+pub struct Watchdog(Arc<Mutex<State>>);
+
+impl Watchdog {
+    pub fn spawn<F>(interval: Duration, mut callback: F) -> Self
+        where F: FnMut(&WatchdogReport) + Send + 'static
+    {
+        let state = Arc::new(Mutex::new(State {
+            last_turn: Instant::now(),
+            turn_started: None,
+            longest_turn: Duration::from_secs(0),
+        }));
+        let weak = Arc::downgrade(&state);
+        thread::spawn(move || watch(weak, interval, &mut callback));
+        Watchdog(state)
+    }
+
+    pub fn start_turn(&self) {
+        self.0.lock().unwrap().turn_started = Some(Instant::now());
+    }
+
+    pub fn end_turn(&self) {
+        let now = Instant::now();
+        let mut state = self.0.lock().unwrap();
+        if let Some(started) = state.turn_started.take() {
+            let elapsed = now - started;
+            if elapsed > state.longest_turn {
+                state.longest_turn = elapsed;
+            }
+        }
+        state.last_turn = now;
+    }
+}
+
+fn watch(state: Weak<Mutex<State>>,
+         interval: Duration,
+         callback: &mut FnMut(&WatchdogReport)) {
+    loop {
+        thread::sleep(interval);
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return,
+        };
+        let report = {
+            let now = Instant::now();
+            let mut state = state.lock().unwrap();
+            let report = WatchdogReport {
+                since_last_turn: now - state.last_turn,
+                current_turn: state.turn_started.map(|started| now - started),
+                longest_turn: state.longest_turn,
+            };
+            state.longest_turn = Duration::from_secs(0);
+            report
+        };
+        // don't keep the state alive (or locked) while the callback runs
+        drop(state);
+        callback(&report);
+    }
+}
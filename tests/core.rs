@@ -0,0 +1,1268 @@
+extern crate futures;
+extern crate synchrotron;
+
+use synchrotron::Void;
+
+#[test]
+fn counters() {
+    let mut core = synchrotron::Core::default();
+    #[cfg(feature = "turn-count")]
+    assert_eq!(core.turns(), 0);
+    assert_eq!(core.completed_tasks(), 0);
+    let handle = core.handle();
+    for _ in 0..3 {
+        handle.spawn(Ok::<(), Void>(())).unwrap();
+    }
+    core.run_unit(Ok(()));
+    #[cfg(feature = "turn-count")]
+    assert!(core.turns() >= 1);
+    core.run(core.handle().spawn_future(Ok::<(), Void>(()))).unwrap();
+    assert_eq!(core.completed_tasks(), 4);
+}
+
+#[test]
+fn injector() {
+    let injector = synchrotron::Injector::new();
+    let remote = injector.clone();
+    std::thread::spawn(move || {
+        remote.push(Ok::<(), Void>(()));
+    }).join().unwrap();
+    let mut core = synchrotron::Core::default();
+    core.add_injector(injector);
+    assert_eq!(core.poll_injected(), 1);
+    assert_eq!(core.poll_injected(), 0);
+    core.run_unit(Ok(()));
+    assert_eq!(core.completed_tasks(), 1);
+}
+
+#[test]
+#[cfg(feature = "watchdog")]
+fn watchdog() {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut core = synchrotron::Core::default();
+    core.set_watchdog(Duration::from_millis(5), move |report| {
+        let _ = sender.send(report.clone());
+    });
+    core.run_unit(futures::future::lazy(|| {
+        thread::sleep(Duration::from_millis(100));
+        Ok(())
+    }));
+    drop(core);
+    let wedged = receiver.iter().any(|report| {
+        report.current_turn.map(|d| d >= Duration::from_millis(20))
+            .unwrap_or(false)
+    });
+    assert!(wedged);
+}
+
+#[test]
+fn fifo_queue() {
+    use synchrotron::{FifoQueue, ReadyQueue};
+
+    let mut queue = FifoQueue::new();
+    queue.push_back(3);
+    queue.push_back(1);
+    queue.push_back(3);
+    queue.push_back(2);
+    queue.remove(1);
+    queue.push_back(1);
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.pop_front(), Some(3));
+    assert_eq!(queue.pop_front(), Some(2));
+    assert_eq!(queue.pop_front(), Some(1));
+    assert_eq!(queue.pop_front(), None);
+    assert!(queue.is_empty());
+
+    // removing and queuing again over and over keeps the order
+    queue.push_back(0);
+    for _ in 0..100 {
+        queue.push_back(1);
+        queue.remove(1);
+    }
+    queue.push_back(2);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop_front(), Some(0));
+    assert_eq!(queue.pop_front(), Some(2));
+    assert_eq!(queue.pop_front(), None);
+
+    let mut core = synchrotron::Core::with_queue(FifoQueue::new());
+    let handle = core.handle();
+    for _ in 0..3 {
+        handle.spawn(Ok::<(), Void>(())).unwrap();
+    }
+    core.run_unit(Ok(()));
+    assert_eq!(core.completed_tasks(), 3);
+}
+
+#[test]
+fn custom_queue() {
+    use std::collections::VecDeque;
+    use synchrotron::ReadyQueue;
+
+    // relies on the default `contains`
+    #[derive(Debug, Default)]
+    struct Queue(VecDeque<usize>);
+
+    impl ReadyQueue for Queue {
+        fn push_back(&mut self, index: usize) {
+            if !self.0.contains(&index) {
+                self.0.push_back(index);
+            }
+        }
+
+        fn pop_front(&mut self) -> Option<usize> {
+            self.0.pop_front()
+        }
+
+        fn remove(&mut self, index: usize) {
+            self.0.retain(|&queued| queued != index);
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    let mut core = synchrotron::Core::with_queue(Queue::default());
+    let handle = core.handle();
+    for _ in 0..3 {
+        handle.spawn(Ok::<(), Void>(())).unwrap();
+    }
+    assert_eq!(core.counts().total, 3);
+    core.run_unit(Ok(()));
+    assert_eq!(core.completed_tasks(), 3);
+}
+
+#[test]
+fn fan_out_wake() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use futures::{Async, future, task};
+
+    let waiting = Rc::new(RefCell::new(Vec::new()));
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    for _ in 0..50 {
+        let waiting = waiting.clone();
+        let mut parked = false;
+        handle.spawn(future::poll_fn(move || {
+            if parked {
+                return Ok::<_, Void>(Async::Ready(()));
+            }
+            parked = true;
+            waiting.borrow_mut().push(task::park());
+            Ok(Async::NotReady)
+        })).unwrap();
+    }
+    core.run_unit(future::poll_fn(|| {
+        if waiting.borrow().len() < 50 {
+            task::park().unpark();
+            return Ok(Async::NotReady);
+        }
+        for task in waiting.borrow_mut().drain(..) {
+            task.unpark();
+        }
+        Ok(Async::Ready(()))
+    }));
+    core.run_unit(Ok(()));
+    assert_eq!(core.completed_tasks(), 50);
+}
+
+#[test]
+#[cfg(feature = "sampler")]
+fn sampler() {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use futures::{Async, future, task};
+
+    let mut core = synchrotron::Core::default();
+    let sampler = core.start_sampler(Duration::from_millis(2), 4);
+    core.handle().spawn(future::empty::<(), Void>()).unwrap();
+    let start = Instant::now();
+    core.run_unit(future::poll_fn(|| {
+        if start.elapsed() > Duration::from_millis(50) {
+            return Ok(Async::Ready(()));
+        }
+        task::park().unpark();
+        Ok(Async::NotReady)
+    }));
+    thread::sleep(Duration::from_millis(10));
+    let history = sampler.history();
+    assert_eq!(history.len(), 4);
+    let latest = sampler.latest().unwrap();
+    assert_eq!(latest.tasks, 1);
+    assert!(latest.turns > 0);
+}
+
+#[test]
+fn pressure() {
+    use std::cell::Cell;
+    use futures::future;
+    use synchrotron::Pressure;
+
+    assert_eq!(synchrotron::pressure(), Pressure::Low);
+    let observed = Cell::new(None);
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    handle.spawn(future::lazy(|| {
+        observed.set(Some(synchrotron::pressure()));
+        Ok::<_, Void>(())
+    })).unwrap();
+    handle.spawn_all((0..300).map(|_| Ok::<(), Void>(()))).unwrap();
+    core.run_unit(Ok(()));
+    assert_eq!(observed.get(), Some(Pressure::High));
+}
+
+#[test]
+fn completions() {
+    use futures::{Stream, future};
+
+    let mut core = synchrotron::Core::default();
+    let completions = core.completions();
+    let handle = core.handle();
+    handle.spawn_all((0..3).map(|_| future::ok::<(), Void>(()))).unwrap();
+    let ids = core.run_ok(completions.take(3).map(|c| c.id()).collect());
+    assert_eq!(ids.len(), 3);
+    assert!(ids[0] < ids[1] && ids[1] < ids[2]);
+
+    let completions = core.completions();
+    drop(core);
+    assert_eq!(completions.wait().count(), 0);
+}
+
+#[test]
+fn completion_outcomes() {
+    use futures::{Stream, future};
+    use synchrotron::TaskOutcome;
+
+    let mut core = synchrotron::Core::default();
+    let completions = core.completions();
+    let handle = core.handle();
+    handle.spawn_named("ok", future::ok::<(), Void>(()));
+    handle.spawn(future::err::<(), _>("oops")).unwrap();
+    handle.spawn_named("stuck", future::empty());
+    core.run_until_stalled();
+    drop(core);
+    let seen: Vec<_> = completions.wait()
+        .map(|c| c.unwrap())
+        .map(|c| (c.name().map(String::from), c.outcome()))
+        .collect();
+    assert_eq!(seen, [(Some("ok".into()), TaskOutcome::Completed),
+                      (None, TaskOutcome::Failed),
+                      (Some("stuck".into()), TaskOutcome::Dropped)]);
+}
+
+#[test]
+fn run_future_core_access() {
+    let mut core = synchrotron::Core::default();
+    let (tx, rx) = futures::sync::oneshot::channel::<()>();
+    let mut run = core.run_future(rx);
+    run.turn();
+    #[cfg(feature = "turn-count")]
+    let turns = run.core().turns();
+    run.core().handle().spawn(futures::future::lazy(move || {
+        let _ = tx.send(());
+        Ok::<_, Void>(())
+    })).unwrap();
+    assert!(run.run().is_ok());
+    #[cfg(feature = "turn-count")]
+    assert!(run.core().turns() > turns);
+    assert_eq!(run.core().completed_tasks(), 1);
+}
+
+#[test]
+fn partitions() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use futures::{Async, future, task};
+
+    let mut core = synchrotron::Core::default();
+    let partitions = core.partition(2);
+    partitions[0].set_budget(1);
+    let done = Rc::new(Cell::new(false));
+    let busy_done = done.clone();
+    partitions[0].core().handle().spawn(future::poll_fn(move || {
+        if busy_done.get() {
+            return Ok(Async::Ready(()));
+        }
+        task::park().unpark();
+        Ok::<_, Void>(Async::NotReady)
+    })).unwrap();
+    for _ in 0..3 {
+        partitions[1].core().handle().spawn(Ok::<(), Void>(())).unwrap();
+    }
+    core.run_unit(future::poll_fn(|| {
+        if partitions[1].core().completed_tasks() < 3 {
+            task::park().unpark();
+            return Ok::<_, Void>(Async::NotReady);
+        }
+        done.set(true);
+        Ok(Async::Ready(()))
+    }));
+    assert_eq!(partitions[0].core().completed_tasks(), 0);
+    #[cfg(feature = "turn-count")]
+    assert!(partitions[0].core().turns() > 0);
+
+    drop(partitions);
+    let mut finished = false;
+    for _ in 0..100 {
+        if let Some(Ok(Async::Ready(()))) = core.turn::<Void>() {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished, "the partitions' drivers did not finish");
+}
+
+#[test]
+fn coop_yields() {
+    use futures::{Async, future};
+
+    let mut core = synchrotron::Core::default();
+    let mut polls = 0;
+    let mut work = 1000;
+    core.run_unit(future::poll_fn(|| {
+        polls += 1;
+        while work > 0 {
+            if let Async::NotReady = synchrotron::coop() {
+                return Ok::<_, Void>(Async::NotReady);
+            }
+            work -= 1;
+        }
+        Ok(Async::Ready(()))
+    }));
+    assert!(polls > 1);
+    assert_eq!(synchrotron::coop(), Async::Ready(()));
+}
+
+#[test]
+fn cooperative_stream() {
+    use futures::{Async, Stream, future, stream};
+    use synchrotron::StreamExt;
+
+    let mut core = synchrotron::Core::default();
+    let mut polls = 0;
+    let mut sum = 0;
+    let mut items = stream::iter_ok::<_, Void>(0..50).cooperative(10);
+    core.run_unit(future::poll_fn(|| {
+        polls += 1;
+        while let Some(item) = futures::try_ready!(items.poll()) {
+            sum += item;
+        }
+        Ok::<_, Void>(Async::Ready(()))
+    }));
+    assert_eq!(sum, 1225);
+    assert_eq!(polls, 6);
+}
+
+#[test]
+#[cfg(feature = "intercept")]
+fn wake_interceptor() {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use futures::{Future, Stream};
+    use synchrotron::{Wake, WakeInterceptor};
+
+    #[derive(Default)]
+    struct Record {
+        unparks: Mutex<Vec<Wake>>,
+        polls: Mutex<Vec<(Wake, Duration)>>,
+    }
+
+    struct Recorder(Arc<Record>);
+
+    impl WakeInterceptor for Recorder {
+        fn on_unpark(&self, wake: &mut Wake) {
+            let mut unparks = self.0.unparks.lock().unwrap();
+            wake.set_tag(unparks.len() as u64 + 1);
+            unparks.push(wake.clone());
+        }
+
+        fn on_poll(&self, wake: &Wake, delay: Duration) {
+            self.0.polls.lock().unwrap().push((wake.clone(), delay));
+        }
+    }
+
+    let mut core = synchrotron::Core::default();
+    let record = Arc::new(Record::default());
+    core.set_wake_interceptor(Recorder(record.clone()));
+    let (tx, rx) = futures::sync::mpsc::unbounded::<()>();
+    core.handle().spawn(rx.for_each(|()| Ok(())).then(|_| Ok::<_, Void>(())))
+        .unwrap();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        drop(tx);
+    });
+    core.run_unit(futures::future::lazy(|| Ok::<(), Void>(())));
+    while core.completed_tasks() == 0 {
+        core.turn::<Void>();
+    }
+    sender.join().unwrap();
+
+    let unparks = record.unparks.lock().unwrap();
+    assert!(unparks.iter().any(|wake| wake.thread() != thread::current().id()));
+    let polls = record.polls.lock().unwrap();
+    assert!(polls.len() >= 2);
+    let last = &polls[polls.len() - 1].0;
+    assert!(last.tag() > 0);
+    assert_ne!(last.thread(), thread::current().id());
+}
+
+#[test]
+#[cfg(feature = "intercept")]
+fn reentrant_interceptor() {
+    use std::sync::{Arc, Mutex};
+    use futures::{Async, future, task};
+    use synchrotron::{Wake, WakeInterceptor};
+
+    // unparks the task it observes once more
+    struct Echo(Arc<Mutex<Option<task::Task>>>, Arc<Mutex<usize>>);
+
+    impl WakeInterceptor for Echo {
+        fn on_unpark(&self, _: &mut Wake) {
+            *self.1.lock().unwrap() += 1;
+            if let Some(ref task) = *self.0.lock().unwrap() {
+                task.unpark();
+            }
+        }
+    }
+
+    let mut core = synchrotron::Core::default();
+    let parked = Arc::new(Mutex::new(None));
+    let calls = Arc::new(Mutex::new(0));
+    core.set_wake_interceptor(Echo(parked.clone(), calls.clone()));
+    let slot = parked.clone();
+    let mut polls = 0;
+    core.handle().spawn(future::poll_fn(move || {
+        polls += 1;
+        if polls == 1 {
+            *slot.lock().unwrap() = Some(task::park());
+            return Ok(Async::NotReady);
+        }
+        Ok::<_, Void>(Async::Ready(()))
+    })).unwrap();
+    core.run_until_stalled();
+    let task = parked.lock().unwrap().clone().unwrap();
+    *calls.lock().unwrap() = 0;
+    task.unpark();
+    assert_eq!(*calls.lock().unwrap(), 1);
+    core.run_until_stalled();
+    assert_eq!(core.completed_tasks(), 1);
+}
+
+#[test]
+#[cfg(feature = "latency")]
+fn wake_latency() {
+    use std::time::Duration;
+    use futures::{Async, task};
+    use futures::future::poll_fn;
+
+    let mut core = synchrotron::Core::default();
+    let latency = core.record_wake_latency();
+    let mut polls = 0;
+    core.run_unit(poll_fn(|| {
+        polls += 1;
+        if polls == 10 {
+            return Ok::<_, Void>(Async::Ready(()));
+        }
+        task::park().unpark();
+        Ok(Async::NotReady)
+    }));
+    let total = latency.total();
+    assert_eq!(total.count(), 10);
+    assert!(total.quantile(0.5) <= total.max());
+    assert!(total.max() < Duration::from_secs(1));
+    assert_eq!(latency.tasks().len(), 1);
+
+    let mut histogram = synchrotron::LatencyHistogram::new();
+    histogram.record(Duration::from_millis(3));
+    histogram.record(Duration::new(0, 100));
+    assert_eq!(histogram.quantile(0.5), Duration::new(0, 128));
+    assert_eq!(histogram.quantile(1.0), Duration::from_millis(3));
+}
+
+#[test]
+fn pacing() {
+    use std::time::{Duration, Instant};
+    use futures::future;
+
+    let mut core = synchrotron::Core::default();
+    core.set_min_turn_interval(Duration::from_millis(2));
+    let start = Instant::now();
+    for _ in 0..6 {
+        core.turn::<Void>();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(10));
+
+    core.set_min_turn_interval(Duration::from_secs(0));
+    core.set_idle_sleep(Duration::from_millis(5));
+    core.handle().spawn(future::empty::<(), Void>()).unwrap();
+    core.turn::<Void>();
+    let start = Instant::now();
+    assert!(core.turn::<Void>().is_none());
+    assert!(start.elapsed() >= Duration::from_millis(5));
+}
+
+#[test]
+fn idle_strategy() {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use futures::Future;
+    use synchrotron::IdleStrategy;
+
+    let mut core = synchrotron::Core::default();
+    core.set_idle_strategy(IdleStrategy {
+        spins: 2,
+        yields: 2,
+        park_timeout: Duration::from_secs(10),
+        spin_below: Duration::from_secs(0),
+    });
+    let (tx, rx) = futures::sync::oneshot::channel::<()>();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        tx.send(()).unwrap();
+    });
+    let start = Instant::now();
+    core.run(rx.map_err(|_| ())).unwrap();
+    // the sender's wake-up unparked the core long before the timeout
+    assert!(start.elapsed() < Duration::from_secs(5));
+    sender.join().unwrap();
+
+    let stats = core.idle_stats();
+    assert!(stats.spins >= 2);
+    assert!(stats.yields >= 2);
+    assert!(stats.parks >= 1);
+    assert!(stats.mean_idle.is_some());
+}
+
+#[test]
+#[cfg(feature = "intercept")]
+fn wake_sources() {
+    use std::thread;
+    use futures::Stream;
+
+    let mut core = synchrotron::Core::default();
+    let sources = core.record_wake_sources();
+    let (tx, rx) = futures::sync::mpsc::unbounded::<u32>();
+    let producer = thread::Builder::new()
+        .name("producer".into())
+        .spawn(move || {
+            for i in 0..3 {
+                tx.unbounded_send(i).unwrap();
+                thread::sleep(std::time::Duration::from_millis(5));
+            }
+        })
+        .unwrap();
+    let sum = core.run(rx.fold(0, |sum, i| Ok::<_, ()>(sum + i))).unwrap();
+    producer.join().unwrap();
+    assert_eq!(sum, 3);
+
+    let found = sources.sources();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].name, Some("producer".to_string()));
+    assert!(found[0].wakes >= 1);
+    assert_eq!(sources.total(), found[0].wakes);
+}
+
+#[test]
+#[cfg(feature = "latency")]
+fn interceptor_pair() {
+    use futures::{Async, task};
+    use futures::future::poll_fn;
+    use synchrotron::WakeLatency;
+
+    let mut core = synchrotron::Core::default();
+    let first = WakeLatency::default();
+    let second = WakeLatency::default();
+    core.set_wake_interceptor((first.clone(), second.clone()));
+    let mut polls = 0;
+    core.run_unit(poll_fn(|| {
+        polls += 1;
+        if polls == 3 {
+            return Ok::<_, Void>(Async::Ready(()));
+        }
+        task::park().unpark();
+        Ok(Async::NotReady)
+    }));
+    assert_eq!(first.total().count(), 3);
+    assert_eq!(second.total().count(), 3);
+}
+
+#[test]
+#[cfg(feature = "lock-stats")]
+fn lock_stats() {
+    use std::thread;
+    use futures::Stream;
+
+    let mut core = synchrotron::Core::default();
+    assert_eq!(core.lock_stats(), synchrotron::LockStats::default());
+    core.enable_lock_stats();
+    let (tx, rx) = futures::sync::mpsc::unbounded::<u32>();
+    let producers: Vec<_> = (0..4).map(|_| {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            for i in 0..100 {
+                tx.unbounded_send(i).unwrap();
+            }
+        })
+    }).collect();
+    drop(tx);
+    let count = core.run(rx.fold(0, |n, _| Ok::<_, ()>(n + 1))).unwrap();
+    for producer in producers {
+        producer.join().unwrap();
+    }
+    assert_eq!(count, 400);
+    let stats = core.lock_stats();
+    assert!(stats.acquisitions > 0);
+    assert!(stats.contended <= stats.acquisitions);
+}
+
+#[test]
+fn counts() {
+    use futures::future;
+    use synchrotron::TaskCounts;
+
+    let mut core = synchrotron::Core::default();
+    assert_eq!(core.counts(), TaskCounts::default());
+    let handle = core.handle();
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    handle.spawn(Ok::<(), Void>(())).unwrap();
+    let counts = core.counts();
+    assert_eq!((counts.total, counts.queued, counts.idle), (2, 2, 0));
+    core.turn::<Void>();
+    core.turn::<Void>();
+    assert_eq!(core.counts(), TaskCounts {
+        total: 1,
+        queued: 0,
+        idle: 1,
+        running: 0,
+    });
+
+    // tasks to be polled first are queued as well
+    handle.spawn_and_poll_now(future::empty());
+    assert_eq!(core.counts().queued, 1);
+    core.turn::<Void>();
+    assert_eq!(core.counts().queued, 0);
+    assert_eq!(core.counts().idle, 2);
+}
+
+#[test]
+fn counts_exclude_main() {
+    use futures::{Async, future, task};
+
+    let mut core = synchrotron::Core::default();
+    // a main future that is always queued again
+    let mut run = core.run_future(future::poll_fn(|| {
+        task::current().notify();
+        Ok::<Async<()>, Void>(Async::NotReady)
+    }));
+    run.core().handle().spawn(future::empty::<(), Void>()).unwrap();
+    run.turn();
+    run.turn();
+    run.core().handle().spawn(future::empty::<(), Void>()).unwrap();
+    let counts = run.core().counts();
+    assert_eq!((counts.total, counts.queued, counts.idle), (2, 1, 1));
+}
+
+#[test]
+fn tasks() {
+    use futures::future;
+    use synchrotron::TaskState;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    core.turn::<Void>();
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    let mut tasks: Vec<_> = core.tasks().collect();
+    tasks.sort_by_key(|task| task.id());
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].state(), TaskState::Idle);
+    assert_eq!(tasks[0].polls(), 1);
+    assert_eq!(tasks[1].state(), TaskState::Queued);
+    assert_eq!(tasks[1].polls(), 0);
+    assert!(tasks[0].age() >= tasks[1].age());
+}
+
+#[test]
+fn poke() {
+    use futures::future;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    while core.turn::<Void>().is_some() {}
+    let mut ids: Vec<_> = core.tasks().map(|task| task.id()).collect();
+    ids.sort();
+    let polls = |core: &synchrotron::Core, id| {
+        core.tasks().find(|task| task.id() == id).unwrap().polls()
+    };
+
+    assert!(core.poke(ids[0]));
+    assert_eq!(core.counts().queued, 1);
+    assert!(core.turn::<Void>().is_some());
+    assert!(core.turn::<Void>().is_none());
+    assert_eq!(polls(&core, ids[0]), 2);
+
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    assert!(core.poke_now(ids[1]));
+    assert_eq!(polls(&core, ids[1]), 2);
+    assert_eq!(core.counts().queued, 1);
+
+    handle.spawn(Ok::<_, Void>(())).unwrap();
+    let last = core.tasks().map(|task| task.id()).max().unwrap();
+    assert!(core.poke_now(last));
+    assert!(!core.poke(last));
+    assert!(!core.poke_now(last));
+    assert_eq!(core.completed_tasks(), 1);
+}
+
+#[test]
+fn wait_handle() {
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use synchrotron::{IdleStrategy, Signal, WaitHandle};
+
+    #[derive(Default)]
+    struct Event(Mutex<bool>, Condvar);
+
+    impl Signal for Event {
+        fn try_wait(&self) -> bool {
+            let mut set = self.0.lock().unwrap();
+            let was_set = *set;
+            *set = false;
+            was_set
+        }
+        fn wait(&self) {
+            let mut set = self.0.lock().unwrap();
+            while !*set {
+                set = self.1.wait(set).unwrap();
+            }
+        }
+    }
+
+    fn signal_later(event: &Arc<Event>) -> thread::JoinHandle<()> {
+        let event = event.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            *event.0.lock().unwrap() = true;
+            event.1.notify_all();
+        })
+    }
+
+    let mut core = synchrotron::Core::default();
+    let event = Arc::new(Event::default());
+    let signaler = signal_later(&event);
+    core.run(WaitHandle::busy(event.clone())).unwrap();
+    signaler.join().unwrap();
+    #[cfg(feature = "turn-count")]
+    assert!(core.turns() > 1);
+
+    core.set_idle_strategy(IdleStrategy {
+        spins: 0,
+        yields: 0,
+        park_timeout: Duration::from_secs(10),
+        spin_below: Duration::from_secs(0),
+    });
+    #[cfg(feature = "turn-count")]
+    let turns = core.turns();
+    let signaler = signal_later(&event);
+    core.run(WaitHandle::blocking(event.clone())).unwrap();
+    signaler.join().unwrap();
+    #[cfg(feature = "turn-count")]
+    assert!(core.turns() - turns < 10);
+    assert!(core.idle_stats().parks >= 1);
+    drop(core);
+
+    // without an idle strategy, nothing waits on the signal for the task
+    let mut core = synchrotron::Core::default();
+    let signaler = signal_later(&event);
+    core.run(WaitHandle::blocking(event.clone())).unwrap();
+    signaler.join().unwrap();
+}
+
+#[test]
+#[cfg(feature = "box-pool")]
+fn pool_stats() {
+    use futures::future;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    assert_eq!(core.pool_stats().hit_rate(), None);
+    for round in 0..10 {
+        for _ in 0..4 {
+            let buffer = [round as u8; 512];
+            handle.spawn(future::lazy(move || {
+                assert_eq!(buffer[511], round as u8);
+                Ok::<_, Void>(())
+            })).unwrap();
+        }
+        // small futures are stored inline and do not use the pool
+        handle.spawn(Ok::<_, Void>(())).unwrap();
+        core.run_unit(Ok(()));
+        while core.counts().total > 0 {
+            core.turn::<Void>();
+        }
+    }
+    let stats = core.pool_stats();
+    assert_eq!(stats.misses, 4);
+    assert_eq!(stats.hits, 36);
+    assert_eq!(stats.hit_rate(), Some(0.9));
+}
+
+#[test]
+fn readiness() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use futures::{Async, Future, future, task};
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    core.run_unit(handle.all_ready());
+    for warmup in 1..4 {
+        let token = handle.ready_token();
+        let mut left = warmup;
+        handle.spawn(future::poll_fn(move || {
+            if left == 0 {
+                return Ok::<_, Void>(Async::Ready(()));
+            }
+            left -= 1;
+            task::park().unpark();
+            Ok(Async::NotReady)
+        }).map(move |()| token.ready())).unwrap();
+    }
+    // only asks for a token once polled
+    let lazy = handle.clone();
+    handle.spawn(future::lazy(move || {
+        lazy.ready_token().ready();
+        Ok::<_, Void>(())
+    })).unwrap();
+    let served = Rc::new(Cell::new(false));
+    let flag = served.clone();
+    handle.spawn(handle.all_ready().map(move |()| flag.set(true))).unwrap();
+    core.turn::<Void>();
+    assert!(!served.get());
+    core.run_until_ready(4);
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    assert!(served.get());
+
+    // nothing is left to wait for on a dropped core
+    drop(core);
+    let mut other = synchrotron::Core::default();
+    let mut run = other.run_future(handle.all_ready());
+    assert_eq!(run.run_until_stalled(), Ok(Async::Ready(())));
+}
+
+#[test]
+fn shutdown() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use futures::{Async, future, task};
+    use synchrotron::ShutdownMode;
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let register = |handle: &synchrotron::Handle, name: &'static str| {
+        let log = log.clone();
+        handle.on_shutdown(move || {
+            let mut turns = 0;
+            future::poll_fn(move || {
+                turns += 1;
+                if turns < 3 {
+                    task::park().unpark();
+                    return Ok(Async::NotReady);
+                }
+                log.borrow_mut().push(name);
+                Ok(Async::Ready(()))
+            })
+        });
+    };
+
+    let core = synchrotron::Core::default();
+    for &name in &["socket", "buffer", "metrics"] {
+        register(&core.handle(), name);
+    }
+    assert!(core.shutdown(ShutdownMode::Drain(Duration::from_secs(60))));
+    assert_eq!(*log.borrow(), ["metrics", "buffer", "socket"]);
+
+    log.borrow_mut().clear();
+    let core = synchrotron::Core::default();
+    register(&core.handle(), "socket");
+    core.handle().on_shutdown(future::empty);
+    assert!(!core.shutdown(ShutdownMode::Drain(Duration::from_millis(10))));
+    assert!(log.borrow().is_empty());
+
+    let core = synchrotron::Core::default();
+    register(&core.handle(), "socket");
+    assert!(!core.shutdown(ShutdownMode::Abort));
+    assert!(log.borrow().is_empty());
+    assert!(synchrotron::Core::default().shutdown(ShutdownMode::Abort));
+}
+
+#[test]
+fn run_with_deadline() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+    use futures::future;
+    use synchrotron::{RunOutcome, ShutdownMode};
+
+    let mut core = synchrotron::Core::default();
+    let far = Instant::now() + Duration::from_secs(60);
+    let drain = ShutdownMode::Drain(Duration::from_secs(60));
+    assert_eq!(core.run_with_deadline(Ok::<_, ()>(42), far, drain),
+               RunOutcome::Finished(Ok(42)));
+
+    let flushed = Rc::new(Cell::new(false));
+    let flag = flushed.clone();
+    let handle = core.handle();
+    handle.on_shutdown(move || future::lazy(move || {
+        flag.set(true);
+        Ok(())
+    }));
+    let abandoned = Rc::new(Cell::new(0));
+    let count = abandoned.clone();
+    core.on_abandoned(move |_| count.set(count.get() + 1));
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    let soon = Instant::now() + Duration::from_millis(10);
+    assert_eq!(core.run_with_deadline(future::empty::<(), ()>(), soon, drain),
+               RunOutcome::Stopped { hooks_completed: true });
+    assert!(flushed.get());
+    assert_eq!(abandoned.get(), 1);
+    assert_eq!(core.counts().total, 0);
+    assert_eq!(core.run(handle.spawn_future(Ok::<_, ()>(7))), Ok(7));
+}
+
+#[test]
+fn task_wakers() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use futures::{Async, future, task};
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let parked = Rc::new(RefCell::new(None));
+    let slot = parked.clone();
+    handle.spawn(future::poll_fn(move || {
+        *slot.borrow_mut() = Some(task::park());
+        Ok::<_, Void>(Async::NotReady)
+    })).unwrap();
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    while core.turn::<Void>().is_some() {}
+    let mut tasks: Vec<_> = core.tasks().collect();
+    tasks.sort_by_key(|task| task.id());
+    assert_eq!(tasks[0].wakers(), 1);
+    assert_eq!(tasks[1].wakers(), 0);
+    parked.borrow_mut().take();
+    assert_eq!(core.tasks().map(|task| task.wakers()).sum::<usize>(), 0);
+}
+
+#[test]
+fn stale_wakeups() {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use futures::{Async, Future, future, task};
+
+    let stale = Rc::new(RefCell::new(None));
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+
+    // a finished task whose slot is reused
+    let slot = stale.clone();
+    handle.spawn(future::poll_fn(move || {
+        *slot.borrow_mut() = Some(task::park());
+        Ok::<_, Void>(Async::Ready(()))
+    })).unwrap();
+    core.turn::<Void>();
+    let polls = Rc::new(Cell::new(0));
+    let count = polls.clone();
+    handle.spawn(future::poll_fn(move || {
+        count.set(count.get() + 1);
+        Ok::<Async<()>, Void>(Async::NotReady)
+    })).unwrap();
+    while core.turn::<Void>().is_some() {}
+    assert_eq!(polls.get(), 1);
+    stale.borrow_mut().take().unwrap().unpark();
+    assert_eq!(core.turn::<Void>(), None);
+    assert_eq!(polls.get(), 1);
+
+    // a main future that was dropped before it finished
+    let slot = stale.clone();
+    {
+        let mut run = core.run_future(future::poll_fn(move || {
+            *slot.borrow_mut() = Some(task::park());
+            Ok::<Async<()>, Void>(Async::NotReady)
+        }));
+        run.turn();
+    }
+    stale.borrow_mut().take().unwrap().unpark();
+    assert_eq!(core.turn::<Void>(), None);
+
+    // tasks dropped along with the core
+    let slot = stale.clone();
+    handle.spawn(future::poll_fn(move || {
+        *slot.borrow_mut() = Some(task::park());
+        Ok::<Async<()>, Void>(Async::NotReady)
+    }).then(|_| Ok::<_, Void>(()))).unwrap();
+    core.turn::<Void>();
+    drop(core);
+    stale.borrow_mut().take().unwrap().unpark();
+}
+
+#[test]
+fn release_at() {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+    use futures::{Async, future, task};
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let started = Rc::new(RefCell::new(Vec::new()));
+    let staged: Vec<_> = (0..3).map(|_| {
+        let started = started.clone();
+        handle.spawn_paused(future::lazy(move || {
+            started.borrow_mut().push(Instant::now());
+            Ok::<(), Void>(())
+        }))
+    }).collect();
+    // a task that keeps running while the release is pending
+    let polls = Rc::new(Cell::new(0));
+    let count = polls.clone();
+    handle.spawn(future::poll_fn(move || {
+        count.set(count.get() + 1);
+        task::park().unpark();
+        Ok::<Async<()>, Void>(Async::NotReady)
+    })).unwrap();
+    let at = Instant::now() + Duration::from_millis(20);
+    let report = core.release_at(at, staged);
+    assert_eq!(report.released, 3);
+    assert!(report.late < Duration::from_millis(20));
+    assert!(report.jitter() >= report.late);
+    assert!(polls.get() > 1);
+    while started.borrow().len() < 3 {
+        core.turn::<Void>();
+    }
+    assert!(started.borrow().iter().all(|&polled| polled >= at));
+
+    // tasks polled first and injected futures are run while waiting as well
+    let mut core = synchrotron::Core::default();
+    let injector = synchrotron::Injector::new();
+    core.add_injector(injector.clone());
+    core.handle().spawn_and_poll_now(Ok(()));
+    injector.push(Ok::<(), Void>(()));
+    let at = Instant::now() + Duration::from_millis(5);
+    core.release_at(at, Vec::new());
+    assert_eq!(core.completed_tasks(), 2);
+}
+
+#[test]
+#[cfg(feature = "turn-context")]
+fn context() {
+    use std::cell::Cell;
+    use futures::{Async, future};
+
+    struct Config {
+        name: &'static str,
+    }
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    core.set_context(Config { name: "first" });
+    core.set_context(Cell::new(0u32));
+    core.set_context(Config { name: "venue" });
+    for _ in 0..3 {
+        handle.spawn(future::lazy(|| {
+            let counter = synchrotron::context::<Cell<u32>>().unwrap();
+            counter.set(counter.get() + 1);
+            Ok::<_, Void>(())
+        })).unwrap();
+    }
+    let name = core.run(future::lazy(|| {
+        let config = synchrotron::context::<Config>().unwrap();
+        assert!(synchrotron::context::<String>().is_none());
+        Ok::<_, ()>(config.name)
+    }));
+    assert_eq!(name, Ok("venue"));
+    while core.turn::<Void>() != Some(Ok(Async::Ready(()))) {}
+    let counter = core.run(future::lazy(|| {
+        Ok::<_, ()>(synchrotron::context::<Cell<u32>>().unwrap().get())
+    }));
+    assert_eq!(counter, Ok(3));
+    assert!(synchrotron::context::<Config>().is_none());
+}
+
+#[test]
+fn reset() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use futures::{Async, Future, future};
+    #[cfg(feature = "box-pool")]
+    use synchrotron::PoolStats;
+    use synchrotron::ShutdownMode;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    for i in 0..4u32 {
+        handle.spawn_collect(future::lazy(move || Ok(i)));
+        handle.spawn(future::empty::<(), Void>()).unwrap();
+        handle.spawn(future::lazy(|| Ok::<_, Void>([0u64; 64])).map(|_| ()))
+            .unwrap();
+    }
+    let hooked = Rc::new(Cell::new(false));
+    let flag = hooked.clone();
+    handle.on_shutdown(move || {
+        flag.set(true);
+        Ok(())
+    });
+    let first = handle.spawn_handle(future::empty::<(), ()>()).id();
+    for _ in 0..20 {
+        core.turn::<Void>();
+    }
+    #[cfg(feature = "turn-count")]
+    assert!(core.turns() > 0);
+    assert_eq!(core.completed_tasks(), 8);
+    assert_eq!(core.tasks().count(), 5);
+    #[cfg(feature = "box-pool")]
+    assert_ne!(core.pool_stats(), PoolStats::default());
+
+    core.reset();
+    #[cfg(feature = "turn-count")]
+    assert_eq!(core.turns(), 0);
+    assert_eq!(core.completed_tasks(), 0);
+    assert_eq!(core.tasks().count(), 0);
+    #[cfg(feature = "box-pool")]
+    assert_eq!(core.pool_stats(), PoolStats::default());
+    assert!(core.take_results::<u32>().is_empty());
+    assert_eq!(core.turn::<Void>(), Some(Ok(Async::Ready(()))));
+
+    let next = handle.spawn_handle(Ok::<_, ()>(1));
+    assert_ne!(next.id(), first);
+    assert_eq!(core.run(next), Ok(1));
+    assert!(core.shutdown(ShutdownMode::Drain(Default::default())));
+    assert!(!hooked.get());
+}
+
+#[test]
+fn core_pool() {
+    use std::cell::Cell;
+    use futures::future;
+    use synchrotron::CorePool;
+
+    let total = Cell::new(0);
+    let pool = CorePool::new(2);
+    assert_eq!(pool.idle(), 0);
+    pool.warm_up(64);
+    assert_eq!(pool.idle(), 2);
+    {
+        let mut first = pool.get();
+        let second = pool.get();
+        let third = pool.get();
+        assert_eq!(pool.idle(), 0);
+        second.handle().spawn(future::empty::<(), Void>()).unwrap();
+        assert_eq!(first.run(Ok::<_, ()>(1)), Ok(1));
+        drop(third);
+        assert_eq!(pool.idle(), 1);
+    }
+    assert_eq!(pool.idle(), 2);
+    let core = pool.get();
+    assert_eq!(core.tasks().count(), 0);
+    #[cfg(feature = "turn-count")]
+    assert_eq!(core.turns(), 0);
+    drop(core);
+
+    for n in 0..3 {
+        let result = pool.scoped_run(|handle| {
+            let total = &total;
+            handle.spawn(future::lazy(move || {
+                total.set(total.get() + n);
+                Ok::<_, Void>(())
+            })).unwrap();
+            Ok::<_, ()>(n)
+        });
+        assert_eq!(result, Ok(n));
+    }
+    assert_eq!(total.get(), 3);
+    assert_eq!(pool.idle(), 2);
+}
+
+#[test]
+fn pooled_handles() {
+    use futures::future;
+    use synchrotron::CorePool;
+
+    let pool = CorePool::new(1);
+    let core = pool.get();
+    #[cfg(feature = "turn-context")]
+    core.enable_current_handle();
+    let stale = core.handle();
+    assert!(stale.is_alive());
+    drop(core);
+
+    let mut core = pool.get();
+    assert!(!stale.is_alive());
+    assert!(!stale.same_core(&core.handle()));
+    assert!(stale.spawn(future::empty::<(), Void>()).is_err());
+    assert_eq!(core.tasks().count(), 0);
+    #[cfg(feature = "turn-context")]
+    {
+        let current = core.run(future::lazy(|| {
+            Ok::<_, ()>(synchrotron::Handle::current().unwrap().is_alive())
+        }));
+        assert_eq!(current, Ok(true));
+    }
+    let fresh = core.handle().spawn_future(Ok::<_, ()>(1));
+    assert_eq!(core.run(fresh), Ok(1));
+}
+
+#[test]
+fn run_until_stalled() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use futures::{Async, Future, future};
+    use futures::sync::oneshot;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let (tx, rx) = oneshot::channel::<u32>();
+    let seen = Rc::new(Cell::new(None));
+    let record = seen.clone();
+    handle.spawn(rx.then(move |result| {
+        record.set(result.ok());
+        Ok::<_, Void>(())
+    })).unwrap();
+    let steps = Rc::new(Cell::new(0));
+    let count = steps.clone();
+    handle.spawn(future::poll_fn(move || {
+        count.set(count.get() + 1);
+        if count.get() < 5 {
+            futures::task::park().unpark();
+            return Ok::<_, Void>(Async::NotReady);
+        }
+        Ok(Async::Ready(()))
+    })).unwrap();
+    core.run_until_stalled();
+    assert_eq!(steps.get(), 5);
+    assert_eq!(seen.get(), None);
+    assert_eq!(core.tasks().count(), 1);
+    tx.send(7).unwrap();
+    core.run_until_stalled();
+    assert_eq!(seen.get(), Some(7));
+    assert_eq!(core.tasks().count(), 0);
+    core.run_until_stalled();
+
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut run = core.run_future(rx);
+    assert_eq!(run.run_until_stalled(), Ok(Async::NotReady));
+    tx.send(3).unwrap();
+    assert_eq!(run.run_until_stalled(), Ok(Async::Ready(3)));
+}
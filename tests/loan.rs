@@ -0,0 +1,49 @@
+extern crate futures;
+extern crate synchrotron;
+
+use futures::{Async, Stream};
+use synchrotron::loan;
+
+#[test]
+fn pipeline() {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let (sender, receiver) = loan::new::<Vec<u8>>();
+    let (results, collected) = loan::new();
+    handle.spawn(receiver.for_each(move |mut message| {
+        message.get_mut().push(0);
+        results.send(message.get().len()).unwrap();
+        Ok(())
+    })).unwrap();
+    for len in 0..3 {
+        sender.send(vec![1; len]).unwrap();
+    }
+    drop(sender);
+    let lengths = core.run(collected.map(loan::Loan::into_inner).collect());
+    assert_eq!(lengths, Ok(vec![1, 2, 3]));
+}
+
+#[test]
+fn closed() {
+    let (sender, receiver) = loan::new();
+    sender.send(1).unwrap();
+    drop(receiver);
+    assert_eq!(sender.send(2), Err(2));
+}
+
+#[test]
+fn independent_loans() {
+    let (sender, mut receiver) = loan::new();
+    sender.send(vec![1]).unwrap();
+    sender.send(vec![2]).unwrap();
+    let (mut first, second) = match (receiver.poll(), receiver.poll()) {
+        (Ok(Async::Ready(Some(first))), Ok(Async::Ready(Some(second)))) => {
+            (first, second)
+        }
+        _ => unreachable!(),
+    };
+    let mut value = first.get_mut();
+    value.push(second.get()[0]);
+    sender.send(vec![3]).unwrap();
+    assert_eq!(*value, vec![1, 2]);
+}
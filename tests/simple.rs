@@ -63,7 +63,7 @@ fn main() {
             assert_eq!(message, "goodbye");
             send(main_inbox, "bye")
         })
-    });
+    }).unwrap();
     core.run({
         send(aux_inbox, "hello").and_then(|()| {
             receive(main_inbox)
@@ -0,0 +1,1256 @@
+extern crate futures;
+extern crate synchrotron;
+
+use std::cell::Cell;
+use futures::{Async, Future, future, task};
+use synchrotron::Void;
+
+#[test]
+fn into_future() {
+    let counter = Cell::new(0);
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    handle.spawn(Ok::<(), Void>(())).unwrap();
+    handle.spawn(future::lazy(|| {
+        counter.set(counter.get() + 1);
+        Ok::<(), Void>(())
+    })).unwrap();
+    assert_eq!(core.run(Ok::<i32, ()>(42)), Ok(42));
+    assert_eq!(core.run(handle.spawn_future(Err::<(), _>("oops"))),
+               Err(synchrotron::JoinError::Failed("oops")));
+    assert_eq!(core.run(future::ok::<(), ()>(())), Ok(()));
+    assert_eq!(counter.get(), 1);
+}
+
+#[test]
+fn run_ok() {
+    let mut core = synchrotron::Core::default();
+    assert_eq!(core.run_ok(future::ok::<_, Void>("hello")), "hello");
+    core.run_unit(Ok(()));
+}
+
+#[test]
+fn run_two() {
+    let polls = &Cell::new(0);
+    let countdown = |n: u32| {
+        let mut n = n;
+        future::poll_fn(move || {
+            polls.set(polls.get() + 1);
+            if n == 0 {
+                return Ok(Async::Ready(polls.get()));
+            }
+            n -= 1;
+            task::park().unpark();
+            Ok::<_, ()>(Async::NotReady)
+        })
+    };
+    let mut core = synchrotron::Core::default();
+    let (a, b) = core.run_two(countdown(3), countdown(10)).unwrap();
+    assert!(a < b);
+    assert_eq!(b, 15);
+}
+
+#[test]
+fn future_sizes() {
+    let warnings = Cell::new(0);
+    let mut core = synchrotron::Core::default();
+    core.set_large_future_threshold(64, |_| warnings.set(warnings.get() + 1));
+    let handle = core.handle();
+    let big = [0u8; 256];
+    handle.spawn(future::lazy(move || {
+        assert_eq!(big.len(), 256);
+        Ok::<(), Void>(())
+    })).unwrap();
+    handle.spawn(future::ok::<(), Void>(())).unwrap();
+    let sizes = core.future_sizes();
+    assert_eq!(sizes.len(), 2);
+    assert!(sizes[0] >= 256);
+    assert!(sizes[1] < 64);
+    assert_eq!(warnings.get(), 1);
+    core.run_unit(Ok(()));
+    assert!(core.future_sizes().is_empty());
+}
+
+#[test]
+fn small_and_large_futures_are_dropped() {
+    use std::rc::Rc;
+
+    let token = Rc::new(());
+    {
+        let mut core = synchrotron::Core::default();
+        let handle = core.handle();
+        let small = token.clone();
+        handle.spawn(future::lazy(move || {
+            drop(small);
+            Ok::<(), Void>(())
+        })).unwrap();
+        let large = (token.clone(), [0u64; 32]);
+        handle.spawn(future::lazy(move || {
+            assert_eq!(large.1.len(), 32);
+            Ok::<(), Void>(())
+        })).unwrap();
+        let pending = token.clone();
+        handle.spawn(future::empty::<(), Void>().map(move |()| {
+            drop(pending);
+        })).unwrap();
+        assert_eq!(Rc::strong_count(&token), 4);
+        core.run_unit(Ok(()));
+        assert_eq!(Rc::strong_count(&token), 2);
+    }
+    assert_eq!(Rc::strong_count(&token), 1);
+}
+
+#[test]
+fn spawn_boxed() {
+    let mut core = synchrotron::Core::default();
+    core.handle().spawn_boxed(Box::new(future::ok(())));
+    core.run_unit(Ok(()));
+    assert_eq!(core.completed_tasks(), 1);
+}
+
+#[test]
+fn spawn_all() {
+    let count = Cell::new(0);
+    let mut core = synchrotron::Core::default();
+    core.handle().spawn_all((0..100).map(|_| future::lazy(|| {
+        count.set(count.get() + 1);
+        Ok::<(), Void>(())
+    }))).unwrap();
+    core.run_unit(Ok(()));
+    assert_eq!(count.get(), 100);
+
+    // the iterator may spawn, and the futures past the limit come back
+    let handle = core.handle();
+    core.set_max_tasks(Some(3));
+    let spawner = handle.clone();
+    let futures = (0..4).map(|i| {
+        if i == 0 {
+            spawner.spawn(future::empty::<(), Void>()).unwrap();
+        }
+        future::empty::<(), Void>()
+    });
+    match handle.spawn_all(futures) {
+        Err(synchrotron::SpawnError::Full(rest)) => assert_eq!(rest.len(), 2),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(core.counts().queued, 3);
+    drop(core);
+    assert!(handle.spawn_all(Some(future::empty::<(), Void>())).is_err());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn handle_affinity() {
+    use std::thread;
+
+    struct AssertSend<T>(T);
+    unsafe impl<T> Send for AssertSend<T> {}
+
+    let core = synchrotron::Core::default();
+    let handle = AssertSend(core.handle());
+    let result = thread::spawn(move || {
+        let handle = handle;
+        handle.0.spawn(Ok::<(), Void>(())).unwrap();
+    }).join();
+    assert!(result.is_err());
+
+    // a handle created on the wrong thread does not help, and neither does
+    // turning the core there
+    let core = AssertSend(synchrotron::Core::default());
+    let result = thread::spawn(move || {
+        let core = core;
+        core.0.handle().spawn(Ok::<(), Void>(())).unwrap();
+    }).join();
+    assert!(result.is_err());
+    let core = AssertSend(synchrotron::Core::default());
+    let result = thread::spawn(move || {
+        let mut core = core;
+        core.0.turn::<Void>();
+    }).join();
+    assert!(result.is_err());
+}
+
+#[test]
+fn spawn_collect() {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    for i in 0..4 {
+        handle.spawn_collect(future::ok::<_, Void>(i));
+    }
+    handle.spawn_collect(future::ok::<_, Void>("done"));
+    core.run_unit(Ok(()));
+    assert_eq!(core.take_results::<i32>(), vec![0, 1, 2, 3]);
+    assert_eq!(core.take_results::<&str>(), vec!["done"]);
+    assert!(core.take_results::<i32>().is_empty());
+}
+
+#[test]
+fn detach() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use futures::Async;
+
+    let mut core = synchrotron::Core::default();
+    let polls = Rc::new(Cell::new(0));
+    let task_polls = polls.clone();
+    let mut run = core.run_future(future::poll_fn(move || {
+        let polls = &task_polls;
+        polls.set(polls.get() + 1);
+        if polls.get() == 3 {
+            return Ok::<_, Void>(Async::Ready(()));
+        }
+        futures::task::park().unpark();
+        Ok(Async::NotReady)
+    }));
+    run.turn();
+    assert_eq!(polls.get(), 1);
+    run.detach();
+    assert_eq!(core.completed_tasks(), 0);
+    core.run_unit(Ok(()));
+    while core.completed_tasks() == 0 {
+        core.turn::<Void>();
+    }
+    assert_eq!(polls.get(), 3);
+}
+
+#[test]
+fn spawn_into() {
+    use std::cell::RefCell;
+
+    let slots: Vec<_> = (0..4).map(|_| RefCell::new(None)).collect();
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    for (i, slot) in slots.iter().enumerate() {
+        handle.spawn_into(slot, future::ok::<_, Void>(i * 10));
+    }
+    core.run_unit(Ok(()));
+    drop(core);
+    let items: Vec<_> = slots.into_iter().map(RefCell::into_inner).collect();
+    assert_eq!(items, vec![Some(0), Some(10), Some(20), Some(30)]);
+}
+
+#[test]
+fn abandoned_tasks_drop_in_reverse() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Guard(u32, Rc<RefCell<Vec<String>>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(format!("drop {}", self.0));
+        }
+    }
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let core = synchrotron::Core::default();
+    let hook_log = log.clone();
+    core.on_abandoned(move |id| {
+        hook_log.borrow_mut().push(format!("abandon {:?}", id));
+    });
+    let handle = core.handle();
+    for i in 0..3 {
+        let guard = Guard(i, log.clone());
+        handle.spawn(future::empty::<(), Void>().map(move |()| drop(guard)))
+            .unwrap();
+    }
+    drop(core);
+    let log = log.borrow();
+    assert_eq!(log.len(), 6);
+    assert!(log[0].starts_with("abandon"));
+    assert_eq!(log[1], "drop 2");
+    assert_eq!(log[3], "drop 1");
+    assert_eq!(log[5], "drop 0");
+}
+
+#[test]
+fn any_join_handles() {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let handles = vec![
+        handle.spawn_future(future::ok::<_, ()>(42u32)).into_any(),
+        handle.spawn_future(future::ok::<_, ()>("forty-two")).into_any(),
+        handle.spawn_future(future::err::<(), _>(-1i64)).into_any(),
+    ];
+    let results = core.run_ok(future::join_all(handles.into_iter().map(|h| {
+        h.then(Ok::<_, Void>)
+    })));
+    let mut results = results.into_iter();
+    let first = results.next().unwrap().unwrap();
+    assert_eq!(first.downcast_ref::<u32>(), Some(&42));
+    let second = results.next().unwrap().unwrap();
+    assert!(second.downcast_ref::<u32>().is_none());
+    assert_eq!(*second.downcast::<&str>().unwrap(), "forty-two");
+    let third = results.next().unwrap().unwrap_err();
+    assert_eq!(third.downcast_ref::<synchrotron::JoinError<i64>>(),
+               Some(&synchrotron::JoinError::Failed(-1)));
+}
+
+#[test]
+fn spawn_records() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use synchrotron::SpawnRecord;
+
+    let records: Rc<RefCell<Vec<SpawnRecord>>> = Default::default();
+    let mut core = synchrotron::Core::default();
+    let hook_records = records.clone();
+    core.on_spawn(move |record| hook_records.borrow_mut().push(record.clone()));
+    let handle = core.handle();
+    let inner_handle = handle.clone();
+    handle.spawn(future::lazy(move || {
+        inner_handle.spawn(Ok::<(), Void>(())).unwrap();
+        Ok::<(), Void>(())
+    })).unwrap();
+    core.run_unit(Ok(()));
+    while core.completed_tasks() < 2 {
+        core.turn::<Void>();
+    }
+
+    let records = records.borrow();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].parent, None);
+    assert!(records[0].type_name.contains("Lazy"));
+    assert_eq!(records[1].parent, Some(records[0].id));
+    assert!(records[1].type_name.contains("FutureResult"));
+}
+
+#[test]
+fn spawn_handle() {
+    use synchrotron::JoinError;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let ok = handle.spawn_handle(future::ok::<_, ()>(7));
+    let err = handle.spawn_handle(future::err::<(), _>("failed"));
+    let never = handle.spawn_handle(future::empty::<(), ()>());
+    assert!(!ok.is_finished());
+    assert_eq!(core.run(ok), Ok(7));
+    assert_eq!(core.run(err), Err(JoinError::Failed("failed")));
+    drop(core);
+    assert!(never.is_finished());
+    assert_eq!(never.wait(), Err(JoinError::Canceled));
+}
+
+#[test]
+fn abort_handle() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use synchrotron::AbortHandle;
+
+    struct Guard(Rc<Cell<bool>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let mut core = synchrotron::Core::default();
+    let dropped = Rc::new(Cell::new(false));
+    let guard = Guard(dropped.clone());
+    let (abort, registration) = AbortHandle::new_pair();
+    core.handle().spawn_abortable(
+        future::empty::<(), Void>().map(move |()| drop(guard)),
+        registration);
+    core.turn::<Void>();
+    assert_eq!(core.turn::<Void>(), None);
+    assert!(!dropped.get());
+
+    let remote = abort.clone();
+    std::thread::spawn(move || remote.abort()).join().unwrap();
+    assert!(abort.is_aborted());
+    core.turn::<Void>();
+    assert!(dropped.get());
+    assert_eq!(core.completed_tasks(), 1);
+    assert_eq!(core.turn::<Void>(), Some(Ok(futures::Async::Ready(()))));
+}
+
+#[test]
+fn spawn_future_cancel_on_drop() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use futures::Async;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let polls = Rc::new(Cell::new(0));
+    let counter = |polls: Rc<Cell<u32>>| future::poll_fn(move || {
+        polls.set(polls.get() + 1);
+        futures::task::park().unpark();
+        Ok::<Async<()>, ()>(Async::NotReady)
+    });
+
+    let mut canceled = handle.spawn_future(counter(polls.clone()));
+    let mut detached = handle.spawn_future(counter(polls.clone()));
+    core.run_unit(future::poll_fn(|| {
+        assert!(canceled.poll().unwrap().is_not_ready());
+        assert!(detached.poll().unwrap().is_not_ready());
+        Ok::<_, Void>(Async::Ready(()))
+    }));
+    drop(canceled);
+    detached.detach();
+    for _ in 0..10 {
+        core.turn::<Void>();
+    }
+    assert_eq!(core.counts().total, 1);
+    assert!(polls.get() >= 5);
+
+    // detaching a future that was never polled still spawns it
+    let lazy = Rc::new(Cell::new(false));
+    let ran = lazy.clone();
+    handle.spawn_future(future::lazy(move || {
+        ran.set(true);
+        Ok::<(), ()>(())
+    })).detach();
+    for _ in 0..10 {
+        core.turn::<Void>();
+    }
+    assert!(lazy.get());
+
+    // the task being dropped with the others cancels the future
+    let mut dropped = handle.spawn_future(future::empty::<(), ()>());
+    core.run_unit(future::poll_fn(|| {
+        assert!(dropped.poll().unwrap().is_not_ready());
+        Ok::<_, Void>(Async::Ready(()))
+    }));
+    core.reset();
+    assert_eq!(dropped.poll(), Err(synchrotron::JoinError::Canceled));
+}
+
+#[test]
+fn cancellation_tokens() {
+    use synchrotron::CancellationToken;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let root = CancellationToken::new();
+    let group = root.child_token();
+    let other = root.child_token();
+    for _ in 0..3 {
+        handle.spawn_cancellable(future::empty(), &group);
+    }
+    handle.spawn_cancellable(future::empty(), &other);
+    let waiter = handle.spawn_handle(group.cancelled());
+    core.turn::<Void>();
+    assert_eq!(core.counts().total, 5);
+
+    group.cancel();
+    assert!(group.is_cancelled());
+    assert!(!root.is_cancelled());
+    assert_eq!(core.run(waiter), Ok(()));
+    assert_eq!(core.counts().total, 1);
+
+    root.cancel();
+    assert!(other.is_cancelled());
+    assert!(root.child_token().is_cancelled());
+    core.run_unit(Ok(()));
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+}
+
+#[test]
+fn scope() {
+    use std::rc::Rc;
+
+    let finished = Rc::new(Cell::new(0));
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let scope = handle.scope(|scope| {
+        for delay in 0..3 {
+            let finished = finished.clone();
+            let mut polls = 0;
+            scope.spawn(future::poll_fn(move || {
+                if polls == delay {
+                    finished.set(finished.get() + 1);
+                    return Ok(Async::Ready(()));
+                }
+                polls += 1;
+                task::park().unpark();
+                Ok(Async::NotReady)
+            }));
+        }
+        assert_eq!(scope.running(), 3);
+        Ok::<_, ()>("done")
+    });
+    assert_eq!(core.run(scope), Ok("done"));
+    assert_eq!(finished.get(), 3);
+
+    let scope = handle.scope(|scope| {
+        for _ in 0..3 {
+            scope.spawn(future::empty());
+        }
+        Err::<(), _>("failed")
+    });
+    assert_eq!(core.run(scope), Err("failed"));
+    assert_eq!(core.counts().total, 0);
+}
+
+#[test]
+fn join_set() {
+    use futures::Stream;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let mut set = handle.join_set();
+    set.spawn(future::empty());
+    let mut polls = 0;
+    set.spawn(future::poll_fn(move || {
+        if polls == 2 {
+            return Err("failed");
+        }
+        polls += 1;
+        task::park().unpark();
+        Ok(Async::NotReady)
+    }));
+    set.spawn(Ok(0));
+    assert_eq!(set.len(), 3);
+    let (first, set) = core.run(set.into_future()).ok().unwrap();
+    assert_eq!(first, Some(0));
+    let (err, mut set) = core.run(set.into_future()).err().unwrap();
+    assert_eq!(err, "failed");
+    assert_eq!(set.len(), 1);
+
+    set.abort_all();
+    set.spawn(Ok(3));
+    assert_eq!(core.run(set.collect()), Ok(vec![3]));
+    assert_eq!(core.counts().total, 0);
+}
+
+#[test]
+fn supervise() {
+    use std::rc::Rc;
+    use synchrotron::{JoinError, Restart, RestartPolicy};
+
+    let starts = Rc::new(Cell::new(0));
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let counter = starts.clone();
+    let supervised = handle.supervise(RestartPolicy::default(), move || {
+        counter.set(counter.get() + 1);
+        if counter.get() < 3 { Err(counter.get()) } else { Ok(()) }
+    });
+    assert_eq!(core.run(supervised), Ok(()));
+    assert_eq!(starts.get(), 3);
+
+    starts.set(0);
+    let counter = starts.clone();
+    let policy = RestartPolicy {
+        restart: Restart::Always,
+        max_restarts: Some(4),
+        ..RestartPolicy::default()
+    };
+    let supervised = handle.supervise(policy, move || {
+        counter.set(counter.get() + 1);
+        Err::<(), _>(counter.get())
+    });
+    assert_eq!(core.run(supervised), Err(JoinError::Failed(5)));
+    assert_eq!(starts.get(), 5);
+}
+
+#[test]
+fn spawn_after() {
+    use std::rc::Rc;
+
+    let order = Rc::new(std::cell::RefCell::new(Vec::new()));
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let mut polls = 0;
+    let log = order.clone();
+    let first = handle.spawn_handle(future::poll_fn(move || {
+        if polls == 3 {
+            log.borrow_mut().push("first");
+            return Ok::<_, Void>(Async::Ready(()));
+        }
+        polls += 1;
+        task::park().unpark();
+        Ok(Async::NotReady)
+    }));
+    let log = order.clone();
+    handle.spawn_after(&first, future::lazy(move || {
+        log.borrow_mut().push("second");
+        Ok(())
+    }));
+    let log = order.clone();
+    handle.spawn(future::lazy(move || {
+        log.borrow_mut().push("unrelated");
+        Ok::<_, Void>(())
+    })).unwrap();
+    core.turn::<Void>();
+    assert_eq!(core.counts().idle, 1);
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    assert!(first.is_finished());
+    assert_eq!(*order.borrow(), ["unrelated", "first", "second"]);
+}
+
+#[test]
+fn spawn_graph() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use synchrotron::TaskGraph;
+
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let step = |name: &'static str| {
+        let order = order.clone();
+        future::lazy(move || {
+            order.borrow_mut().push(name);
+            Ok::<(), &str>(())
+        })
+    };
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let mut graph = TaskGraph::new();
+    let fetch_a = graph.add(&[], step("fetch a"));
+    let fetch_b = graph.add(&[], step("fetch b"));
+    let merge = graph.add(&[fetch_b, fetch_a], step("merge"));
+    graph.add(&[merge], step("report"));
+    assert_eq!(graph.len(), 4);
+    assert_eq!(core.run(handle.spawn_graph(graph)), Ok(()));
+    assert_eq!(*order.borrow(), ["fetch a", "fetch b", "merge", "report"]);
+
+    order.borrow_mut().clear();
+    let mut graph = TaskGraph::new();
+    let fetch = graph.add(&[], step("fetch"));
+    graph.add(&[], future::empty());
+    let check = graph.add(&[fetch], Err("invalid"));
+    graph.add(&[check], step("report"));
+    assert_eq!(core.run(handle.spawn_graph(graph)), Err("invalid"));
+    assert_eq!(*order.borrow(), ["fetch"]);
+    assert_eq!(core.counts().total, 0);
+}
+
+#[test]
+fn spawn_with_handle() {
+    let counter = Cell::new(0);
+    let mut core = synchrotron::Core::default();
+    core.handle().spawn_with_handle(|handle| {
+        for _ in 0..3 {
+            handle.spawn_with_handle(|handle| {
+                handle.spawn(future::lazy(|| {
+                    counter.set(counter.get() + 1);
+                    Ok::<_, Void>(())
+                })).unwrap();
+                Ok(())
+            });
+        }
+        Ok(())
+    });
+    core.run_unit(Ok(()));
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    assert_eq!(counter.get(), 3);
+}
+
+#[test]
+fn spawn_fn() {
+    let counter = Cell::new(0);
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    handle.spawn_fn(|| {
+        counter.set(counter.get() + 1);
+        Ok(())
+    });
+    assert_eq!(counter.get(), 0);
+    core.turn::<Void>();
+    assert_eq!(counter.get(), 1);
+}
+
+#[test]
+#[cfg(feature = "turn-context")]
+fn turn_context() {
+    use std::rc::Rc;
+    use synchrotron::{Core, TaskId};
+
+    thread_local! {
+        static EVENTS: Cell<u32> = Cell::new(0);
+    }
+
+    // stands in for a callback registered with a C library, which can only
+    // reach the core through the turn context
+    fn on_event(waiter: TaskId) {
+        Core::with_turn_context(|context| {
+            context.spawn(future::lazy(|| {
+                EVENTS.with(|events| events.set(events.get() + 1));
+                Ok(())
+            }));
+            context.wake(waiter);
+        }).expect("called outside of a poll");
+    }
+
+    assert!(Core::with_turn_context(|_| ()).is_none());
+    let mut core = Core::default();
+    let handle = core.handle();
+    let woken = Rc::new(Cell::new(0));
+    let counter = woken.clone();
+    handle.spawn(future::poll_fn(move || {
+        counter.set(counter.get() + 1);
+        Ok::<_, Void>(Async::NotReady)
+    })).unwrap();
+    core.turn::<Void>();
+    let waiter = core.tasks().next().unwrap().id();
+    handle.spawn(future::lazy(move || {
+        on_event(waiter);
+        Ok::<_, Void>(())
+    })).unwrap();
+    while core.turn::<Void>().is_some() {}
+    assert_eq!(EVENTS.with(Cell::get), 1);
+    assert_eq!(woken.get(), 2);
+}
+
+#[test]
+#[cfg(feature = "turn-context")]
+fn turn_context_wake_main() {
+    use synchrotron::{Core, TaskId};
+
+    let mut core = Core::default();
+    let handle = core.handle();
+    let polls = Cell::new(0);
+    let mut run = core.run_future(future::poll_fn(|| {
+        polls.set(polls.get() + 1);
+        if polls.get() > 1 {
+            return Ok::<_, Void>(Async::Ready(()));
+        }
+        handle.spawn(future::lazy(|| {
+            Core::with_turn_context(|context| context.wake(TaskId::main()));
+            Ok::<_, Void>(())
+        })).unwrap();
+        Ok(Async::NotReady)
+    }));
+    assert_eq!(run.run_until_stalled(), Ok(Async::Ready(())));
+    assert_eq!(polls.get(), 2);
+}
+
+#[test]
+fn spawn_errors() {
+    use std::cell::RefCell;
+
+    let errors = RefCell::new(Vec::new());
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    handle.spawn(Err::<(), _>("ignored")).unwrap();
+    core.run_unit(Ok(()));
+    core.on_task_error(|task, err| {
+        errors.borrow_mut().push((task, format!("{:?}", err)));
+    });
+    handle.spawn(Ok::<_, u32>(())).unwrap();
+    handle.spawn(future::lazy(|| Err::<(), _>(42))).unwrap();
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    let errors = errors.borrow();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].1, "42");
+}
+
+#[test]
+fn spawn_on_dropped_core() {
+    use synchrotron::SpawnError;
+
+    let core = synchrotron::Core::default();
+    let handle = core.handle();
+    assert!(handle.spawn(Ok::<_, Void>(())).is_ok());
+    drop(core);
+    match handle.spawn(Ok::<_, Void>(())) {
+        Err(err @ SpawnError::Shutdown(_)) => {
+            assert_eq!(err.to_string(), "core was dropped");
+            assert_eq!(err.into_inner(), Ok(()));
+        }
+        _ => panic!("spawned on a dropped core"),
+    }
+}
+
+#[test]
+fn strong_handle() {
+    use std::rc::Rc;
+
+    struct Guard(Rc<Cell<bool>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let dropped = Rc::new(Cell::new(false));
+    let core = synchrotron::Core::default();
+    let weak = core.handle();
+    let strong = weak.to_strong().unwrap();
+    // a task holding a strong handle to its own core is dropped with it
+    let own = core.strong_handle();
+    let guard = Guard(dropped.clone());
+    strong.spawn(future::empty::<(), Void>().then(move |_| {
+        drop((own, guard));
+        Ok::<_, Void>(())
+    })).unwrap();
+    drop(core);
+    assert!(dropped.get());
+    dropped.set(false);
+    assert!(weak.spawn(Ok::<_, Void>(())).is_ok());
+    let guard = Guard(dropped.clone());
+    assert!(strong.spawn(future::empty::<(), Void>().then(move |_| {
+        drop(guard);
+        Ok::<_, Void>(())
+    })).is_ok());
+    assert!(!dropped.get());
+    drop(strong);
+    assert!(dropped.get());
+    assert!(weak.to_strong().is_none());
+    assert!(weak.spawn(Ok::<_, Void>(())).is_err());
+}
+
+#[test]
+fn spawn_after_task() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use synchrotron::JoinError;
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let step = |name: &'static str, ok: bool| {
+        let log = log.clone();
+        future::lazy(move || {
+            log.borrow_mut().push(name);
+            if ok { Ok(name) } else { Err(name) }
+        })
+    };
+    let connect = handle.spawn_handle(step("connect", true));
+    let auth = handle.spawn_after_task(&connect, step("auth", true));
+    let subscribe = handle.spawn_after_task(&auth, step("subscribe", true));
+    let retry = handle.spawn_handle(step("retry", false));
+    let login = handle.spawn_after_task(&retry, step("login", true));
+    let fetch = handle.spawn_after_task(&login, step("fetch", true));
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    assert_eq!(*log.borrow(), ["connect", "retry", "auth", "subscribe"]);
+    assert_eq!(core.run(connect.join(auth).join(subscribe)),
+               Ok((("connect", "auth"), "subscribe")));
+    assert_eq!(core.run(retry), Err(JoinError::Failed("retry")));
+    assert_eq!(core.run(login), Err(JoinError::Canceled));
+    assert_eq!(core.run(fetch), Err(JoinError::Canceled));
+}
+
+#[test]
+fn handle_identity() {
+    let core = synchrotron::Core::default();
+    let other = synchrotron::Core::default();
+    let handle = core.handle();
+    assert!(handle.is_alive());
+    assert!(handle.same_core(&handle.clone()));
+    assert!(handle.same_core(&core.strong_handle()));
+    assert!(!handle.same_core(&other.handle()));
+    drop(core);
+    assert!(!handle.is_alive());
+    assert!(other.handle().is_alive());
+    assert!(!handle.same_core(&other.handle()));
+}
+
+#[test]
+fn core_spawn() {
+    let counter = Cell::new(0);
+    let mut core = synchrotron::Core::default();
+    core.spawn(future::lazy(|| {
+        counter.set(counter.get() + 1);
+        Ok::<_, Void>(())
+    })).unwrap();
+    let answer = core.spawn_handle(Ok::<_, ()>(42));
+    assert_eq!(core.run(answer), Ok(42));
+    assert_eq!(counter.get(), 1);
+}
+
+#[test]
+fn max_tasks() {
+    use synchrotron::SpawnError;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    core.set_max_tasks(Some(2));
+    assert!(handle.spawn(future::empty::<(), Void>()).is_ok());
+    assert!(handle.spawn(Ok::<_, Void>(())).is_ok());
+    match handle.spawn(Ok::<_, Void>(())) {
+        Err(err @ SpawnError::Full(_)) => {
+            assert_eq!(err.to_string(), "core is at its task limit");
+            assert_eq!(err.into_inner(), Ok(()));
+        }
+        _ => panic!("spawned beyond the limit"),
+    }
+    // spawns that cannot hand the future back are not limited
+    handle.spawn_fn(|| Ok(()));
+    assert_eq!(core.counts().total, 3);
+    while core.counts().total > 1 {
+        core.turn::<Void>();
+    }
+    assert!(handle.spawn(Ok::<_, Void>(())).is_ok());
+    core.set_max_tasks(None);
+    assert!(handle.spawn(Ok::<_, Void>(())).is_ok());
+}
+
+#[test]
+fn spawn_when_ready() {
+    use std::rc::Rc;
+    use futures::stream::{self, Stream};
+    use synchrotron::SpawnError;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    core.set_max_tasks(Some(2));
+    let running = Rc::new(Cell::new(0));
+    let peak = Rc::new(Cell::new(0));
+    let spawned = Rc::new(Cell::new(0));
+    let producer = stream::iter_ok::<_, SpawnError<_>>(0..6).for_each(|_| {
+        let running = running.clone();
+        let peak = peak.clone();
+        let mut polls = 0;
+        spawned.set(spawned.get() + 1);
+        handle.spawn_when_ready(future::poll_fn(move || {
+            polls += 1;
+            if polls == 1 {
+                running.set(running.get() + 1);
+                peak.set(std::cmp::max(peak.get(), running.get()));
+            }
+            if polls < 3 {
+                task::park().unpark();
+                return Ok::<_, Void>(Async::NotReady);
+            }
+            running.set(running.get() - 1);
+            Ok(Async::Ready(()))
+        }))
+    });
+    assert!(core.run(producer).is_ok());
+    assert_eq!(spawned.get(), 6);
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    assert_eq!(running.get(), 0);
+    assert_eq!(peak.get(), 2);
+}
+
+#[test]
+fn spawn_when_ready_wakes() {
+    use synchrotron::{Core, SpawnError};
+
+    let mut core = Core::default();
+    let handle = core.handle();
+    let mut driver = Core::default();
+    core.set_max_tasks(Some(1));
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+
+    // raising the limit
+    let mut run = driver.run_future(handle.spawn_when_ready(Ok::<_, Void>(())));
+    assert_eq!(run.run_until_stalled().ok(), Some(Async::NotReady));
+    core.set_max_tasks(Some(2));
+    assert_eq!(run.run_until_stalled().ok(), Some(Async::Ready(())));
+    drop(run);
+
+    // dropping the tasks
+    let mut run = driver.run_future(handle.spawn_when_ready(Ok::<_, Void>(())));
+    assert_eq!(run.run_until_stalled().ok(), Some(Async::NotReady));
+    core.reset();
+    assert_eq!(run.run_until_stalled().ok(), Some(Async::Ready(())));
+    drop(run);
+
+    // dropping the core
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    let mut run = driver.run_future(handle.spawn_when_ready(Ok::<_, Void>(())));
+    assert_eq!(run.run_until_stalled().ok(), Some(Async::NotReady));
+    drop(core);
+    match run.run_until_stalled() {
+        Err(SpawnError::Shutdown(_)) => {}
+        _ => panic!("spawned on a dropped core"),
+    }
+}
+
+#[test]
+fn spawn_future_inline() {
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let mut run = core.run_future(handle.spawn_future_inline(Ok::<_, ()>(42)));
+    assert_eq!(run.turn(), Some(Ok(Async::Ready(42))));
+    drop(run);
+    assert_eq!(core.completed_tasks(), 0);
+    let mut polled = false;
+    assert_eq!(core.run(handle.spawn_future_inline(future::poll_fn(move || {
+        if polled {
+            return Ok::<_, ()>(Async::Ready(7));
+        }
+        polled = true;
+        task::park().unpark();
+        Ok(Async::NotReady)
+    }))), Ok(7));
+    assert_eq!(core.completed_tasks(), 1);
+}
+
+#[test]
+fn spawn_and_poll_now() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let step = |name: &'static str| {
+        let log = log.clone();
+        future::lazy(move || {
+            log.borrow_mut().push(name);
+            Ok(())
+        })
+    };
+    handle.spawn(step("queued")).unwrap();
+    core.spawn_and_poll_now(step("now"));
+    assert_eq!(*log.borrow(), ["now"]);
+    handle.spawn_and_poll_now(step("next turn"));
+    core.turn::<Void>();
+    assert_eq!(*log.borrow(), ["now", "next turn", "queued"]);
+    assert_eq!(core.completed_tasks(), 3);
+}
+
+#[test]
+#[cfg(feature = "turn-context")]
+fn current_handle() {
+    use std::rc::Rc;
+    use synchrotron::Handle;
+
+    let spawned = Rc::new(Cell::new(0));
+    let mut core = synchrotron::Core::default();
+    let count = spawned.clone();
+    let spawn_sibling = move || {
+        if let Some(handle) = Handle::current() {
+            let count = count.clone();
+            handle.spawn(future::lazy(move || {
+                count.set(count.get() + 1);
+                Ok::<_, Void>(())
+            })).unwrap();
+        }
+        Ok::<(), Void>(())
+    };
+    assert!(Handle::current().is_none());
+    core.run_unit(future::lazy(spawn_sibling.clone()));
+    core.handle().spawn(future::lazy(spawn_sibling.clone())).unwrap();
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    assert_eq!(spawned.get(), 0);
+    core.enable_current_handle();
+    core.run_unit(future::lazy(spawn_sibling.clone()));
+    core.handle().spawn(future::lazy(spawn_sibling)).unwrap();
+    while core.counts().total > 0 {
+        core.turn::<Void>();
+    }
+    assert_eq!(spawned.get(), 2);
+    assert!(Handle::current().is_none());
+}
+
+#[test]
+#[cfg(feature = "turn-context")]
+fn task_ids() {
+    use std::rc::Rc;
+    use synchrotron::{TaskId, current_task_id};
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    assert_eq!(current_task_id(), None);
+    let seen = Rc::new(Cell::new(None));
+    let record = seen.clone();
+    let task = handle.spawn_handle(future::lazy(move || {
+        record.set(current_task_id());
+        Ok::<_, ()>(())
+    }));
+    let id = task.id();
+    assert!(id.is_some());
+    assert_eq!(core.run(future::lazy(|| Ok::<_, ()>(current_task_id()))),
+               Ok(Some(TaskId::main())));
+    assert_eq!(core.run(task), Ok(()));
+    assert_eq!(seen.get(), id);
+    // the slot of the finished task is reused under its next generation
+    let reused = core.spawn_handle(Ok::<_, ()>(())).id();
+    assert_ne!(reused, id);
+    assert_eq!(id.map(|id| id.as_u64()), Some(1));
+    assert_eq!(reused.map(|id| id.as_u64()), Some(1 << 32 | 1));
+    drop(core);
+    assert_eq!(handle.spawn_handle(Ok::<_, ()>(())).id(), None);
+}
+
+#[test]
+fn spawn_named() {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let names = Rc::new(RefCell::new(Vec::new()));
+    let log = names.clone();
+    core.on_spawn(move |record| log.borrow_mut().push(record.name.clone()));
+    handle.spawn_named("ticker", future::empty());
+    handle.spawn_named(format!("worker-{}", 1), future::empty());
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    core.turn::<Void>();
+    let mut tasks: Vec<_> = core.tasks().collect();
+    tasks.sort_by_key(|task| task.id());
+    let listed: Vec<_> = tasks.iter().map(|task| task.name()).collect();
+    assert_eq!(listed, [Some("ticker"), Some("worker-1"), None]);
+    assert_eq!(names.borrow().len(), 3);
+    assert_eq!(names.borrow()[0].as_deref(), Some("ticker"));
+    let debug = format!("{:?}", core);
+    assert!(debug.contains("\"ticker\""));
+    assert!(debug.contains("\"worker-1\""));
+}
+
+#[test]
+fn spawn_paused() {
+    use std::rc::Rc;
+    use std::thread;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let polls = Rc::new(Cell::new(0));
+    let staged: Vec<_> = (0..3).map(|_| {
+        let count = polls.clone();
+        handle.spawn_paused(future::lazy(move || {
+            count.set(count.get() + 1);
+            Ok::<(), Void>(())
+        }))
+    }).collect();
+    assert!(staged.iter().all(|task| task.id().is_some()));
+    while core.turn::<Void>().is_some() {}
+    assert_eq!(polls.get(), 0);
+    thread::spawn(move || {
+        for task in staged {
+            task.start();
+        }
+    }).join().unwrap();
+    while core.turn::<Void>() != Some(Ok(Async::Ready(()))) {}
+    assert_eq!(polls.get(), 3);
+    drop(core);
+    let task = handle.spawn_paused(future::empty());
+    assert_eq!(task.id(), None);
+    task.start();
+}
+
+#[test]
+fn spawn_daemon() {
+    use std::rc::Rc;
+
+    struct Flag(Rc<Cell<bool>>);
+
+    impl Drop for Flag {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let dropped = Rc::new(Cell::new(false));
+    let flag = Flag(dropped.clone());
+    handle.spawn_daemon(future::poll_fn(move || {
+        let _ = &flag;
+        task::park().unpark();
+        Ok::<Async<()>, Void>(Async::NotReady)
+    }));
+    let done = Rc::new(Cell::new(0));
+    let count = done.clone();
+    let mut left = 10;
+    handle.spawn(future::poll_fn(move || {
+        if left == 0 {
+            count.set(count.get() + 1);
+            return Ok::<_, Void>(Async::Ready(()));
+        }
+        left -= 1;
+        task::park().unpark();
+        Ok(Async::NotReady)
+    })).unwrap();
+    assert_eq!(core.run_until_done(Ok::<_, ()>(42)), Ok(42));
+    assert_eq!(done.get(), 1);
+    assert!(dropped.get());
+    assert_eq!(core.tasks().count(), 0);
+
+    // a task that nothing will wake is left behind instead of waited for
+    handle.spawn(future::empty::<(), Void>()).unwrap();
+    assert_eq!(core.run_until_done(Ok::<_, ()>(7)), Ok(7));
+    assert_eq!(core.tasks().count(), 1);
+}
+
+#[test]
+fn run_all() {
+    use std::rc::Rc;
+
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    let done = Rc::new(Cell::new(0));
+    for n in 0..5 {
+        let count = done.clone();
+        let inner = handle.clone();
+        let mut left = n;
+        handle.spawn(future::poll_fn(move || {
+            if left > 0 {
+                left -= 1;
+                task::park().unpark();
+                return Ok::<_, Void>(Async::NotReady);
+            }
+            let count = count.clone();
+            inner.spawn(future::lazy(move || {
+                count.set(count.get() + 1);
+                Ok::<_, Void>(())
+            })).unwrap();
+            Ok(Async::Ready(()))
+        })).unwrap();
+    }
+    core.run_all();
+    assert_eq!(done.get(), 5);
+    assert_eq!(core.tasks().count(), 0);
+    core.run_all();
+}
+
+#[test]
+fn scoped_run() {
+    use std::rc::Rc;
+
+    let total = Cell::new(0);
+    let result = synchrotron::scoped_run(|handle| {
+        for n in 1..4 {
+            let total = &total;
+            handle.spawn(future::lazy(move || {
+                total.set(total.get() + n);
+                Ok::<_, Void>(())
+            })).unwrap();
+        }
+        handle.spawn_daemon(future::empty());
+        handle.spawn_future(future::lazy(|| Ok::<_, ()>(42)))
+    });
+    assert_eq!(result, Ok(42));
+    assert_eq!(total.get(), 6);
+
+    let held = Rc::new(());
+    let task = held.clone();
+    let result = synchrotron::scoped_run(move |handle| {
+        handle.spawn(future::empty::<(), Void>().map(move |()| drop(task)))
+            .unwrap();
+        Err::<(), _>("oops")
+    });
+    assert_eq!(result, Err("oops"));
+    assert_eq!(Rc::strong_count(&held), 1);
+}
+
+#[test]
+fn infallible() {
+    use std::convert::Infallible;
+
+    let counter = Cell::new(0);
+    let mut core = synchrotron::Core::default();
+    let handle = core.handle();
+    handle.spawn_infallible(future::lazy(|| {
+        counter.set(counter.get() + 1);
+        Ok::<(), Infallible>(())
+    }));
+    let item = core.run_infallible(future::lazy(|| {
+        Ok::<_, Infallible>(42)
+    }));
+    assert_eq!(item, 42);
+    core.run_all();
+    assert_eq!(counter.get(), 1);
+}
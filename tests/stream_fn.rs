@@ -0,0 +1,32 @@
+extern crate futures;
+extern crate synchrotron;
+
+use futures::{Async, Stream, task};
+use synchrotron::{stream_fn, unfold_local};
+
+#[test]
+fn stream_fn_yields_until_done() {
+    let mut core = synchrotron::Core::default();
+    let mut polls = 0;
+    let stream = stream_fn(|| {
+        polls += 1;
+        if polls % 2 == 0 {
+            // nothing yet: retry on the next turn
+            task::park().unpark();
+            return Ok::<_, ()>(Async::NotReady);
+        }
+        Ok(Async::Ready(if polls < 7 { Some(polls) } else { None }))
+    });
+    assert_eq!(core.run(stream.collect()), Ok(vec![1, 3, 5]));
+}
+
+#[test]
+fn unfold_local_keeps_state() {
+    let mut core = synchrotron::Core::default();
+    let mut stream = unfold_local(vec![1, 2, 3], |items: &mut Vec<u32>| {
+        Ok::<_, ()>(Async::Ready(items.pop()))
+    });
+    let first = core.run(futures::future::poll_fn(|| stream.poll()));
+    assert_eq!(first, Ok(Some(3)));
+    assert_eq!(stream.into_state(), vec![1, 2]);
+}
@@ -0,0 +1,32 @@
+extern crate futures;
+extern crate synchrotron;
+
+use futures::{Async, Stream, future, task};
+use synchrotron::LocalFuturesUnordered;
+
+#[test]
+fn completion_order() {
+    let mut set = LocalFuturesUnordered::new();
+    for &n in &[5, 1, 3, 0] {
+        let mut remaining = n;
+        set.push(future::poll_fn(move || {
+            if remaining == 0 {
+                return Ok(Async::Ready(n));
+            }
+            remaining -= 1;
+            task::park().unpark();
+            Ok::<_, ()>(Async::NotReady)
+        }));
+    }
+    assert_eq!(set.len(), 4);
+    let mut core = synchrotron::Core::default();
+    assert_eq!(core.run(set.collect()), Ok(vec![0, 1, 3, 5]));
+}
+
+#[test]
+fn empty() {
+    let set = LocalFuturesUnordered::<future::Empty<(), ()>>::new();
+    assert!(set.is_empty());
+    let mut core = synchrotron::Core::default();
+    assert_eq!(core.run(set.collect()), Ok(vec![]));
+}